@@ -8,7 +8,7 @@
 use std::sync::Arc;
 
 use jsonrpsee::RpcModule;
-use recipies_runtime::{opaque::Block, AccountId, Balance, Nonce};
+use recipies_runtime::{opaque::Block, AccountId, Balance, BlockNumber, Nonce};
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
@@ -36,17 +36,26 @@ where
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: double_map_rpc::DoubleMapRuntimeApi<Block, AccountId>,
+	C::Api: last_caller_rpc::LastCallerRuntimeApi<Block, AccountId, BlockNumber>,
+	C::Api: simple_crowdfund_rpc::SimpleCrowdfundRuntimeApi<Block, AccountId, Balance, BlockNumber>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
 {
+	use double_map_rpc::{DoubleMap, DoubleMapApiServer};
+	use last_caller_rpc::{LastCaller, LastCallerApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
+	use simple_crowdfund_rpc::{SimpleCrowdfund, SimpleCrowdfundApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
 	let FullDeps { client, pool, deny_unsafe } = deps;
 
 	module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(DoubleMap::new(client.clone()).into_rpc())?;
+	module.merge(LastCaller::new(client.clone()).into_rpc())?;
+	module.merge(SimpleCrowdfund::new(client).into_rpc())?;
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
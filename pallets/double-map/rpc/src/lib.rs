@@ -0,0 +1,96 @@
+//! RPC interface for the double-map pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+pub use double_map_runtime_api::DoubleMapApi as DoubleMapRuntimeApi;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+
+#[rpc(client, server)]
+pub trait DoubleMapApi<BlockHash, AccountId> {
+	/// The `k` highest scoring members of `group`, best first.
+	#[method(name = "doubleMap_topMembers")]
+	fn top_members(
+		&self,
+		group: u32,
+		k: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(AccountId, u32)>>;
+
+	/// Up to `limit` members of `group`, resuming after the `start` account.
+	#[method(name = "doubleMap_groupMembers")]
+	fn group_members(
+		&self,
+		group: u32,
+		start: Option<AccountId>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(AccountId, u32)>>;
+}
+
+/// Provides RPC methods to query the double-map pallet's leaderboards.
+pub struct DoubleMap<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> DoubleMap<C, Block> {
+	/// Create new `DoubleMap` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block, AccountId> DoubleMapApiServer<<Block as BlockT>::Hash, AccountId>
+	for DoubleMap<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: DoubleMapRuntimeApi<Block, AccountId>,
+	AccountId: Codec + Send + Sync + 'static,
+{
+	fn top_members(
+		&self,
+		group: u32,
+		k: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(AccountId, u32)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.top_members(at, group, k).map_err(runtime_error_into_rpc_err)
+	}
+
+	fn group_members(
+		&self,
+		group: u32,
+		start: Option<AccountId>,
+		limit: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(AccountId, u32)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.group_members(at, group, start, limit).map_err(runtime_error_into_rpc_err)
+	}
+}
+
+/// Error code for runtime errors.
+const RUNTIME_ERROR: i32 = 1;
+
+/// Converts a runtime trap into an RPC error.
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		RUNTIME_ERROR,
+		"Runtime error",
+		Some(format!("{:?}", err)),
+	))
+	.into()
+}
@@ -0,0 +1,15 @@
+//! Runtime API definition for the double-map pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait DoubleMapApi<AccountId> where AccountId: Codec {
+		/// The `k` highest scoring members of `group` with their scores, best first.
+		fn top_members(group: u32, k: u32) -> Vec<(AccountId, u32)>;
+		/// Up to `limit` members of `group` with their scores, starting after `start`.
+		fn group_members(group: u32, start: Option<AccountId>, limit: u32) -> Vec<(AccountId, u32)>;
+	}
+}
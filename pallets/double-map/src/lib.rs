@@ -1,6 +1,9 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use account_set::WeightedAccountSet;
+use frame_support::{traits::Get, weights::Weight};
 pub use pallet::*;
+use sp_std::{collections::btree_map::BTreeMap, marker::PhantomData, vec::Vec};
 
 #[cfg(test)]
 mod mock;
@@ -12,6 +15,7 @@ mod tests;
 pub mod pallet {
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
+	use sp_std::vec::Vec;
 
 	#[pallet::pallet]
 	pub struct Pallet<T>(_);
@@ -19,6 +23,11 @@ pub mod pallet {
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The maximum number of scores removed by a single `remove_group_score` or
+		/// `continue_clear` call, and by `on_idle` in a single block.
+		#[pallet::constant]
+		type MaxRemovalsPerCall: Get<u32>;
 	}
 
 	pub type GroupIndex = u32;
@@ -35,24 +44,72 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// Reverse index of `MemberScore`: the groups each account has joined.
+	///
+	/// Keyed by account first so that all of a member's groups can be found with a single
+	/// prefix iteration when the member leaves.
 	#[pallet::storage]
-	#[pallet::getter(fn group_membership)]
-	pub type GroupMembership<T: Config> =
-		StorageMap<_, Blake2_128Concat, T::AccountId, GroupIndex, ValueQuery>;
+	pub type GroupMembership<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		GroupIndex,
+		(),
+		ValueQuery,
+	>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn all_members)]
 	pub type AllMembers<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
 
+	/// Groups that are being removed, with the raw `MemberScore` key of the last score that was
+	/// cleared. An empty cursor means that nothing has been cleared yet.
+	#[pallet::storage]
+	#[pallet::getter(fn clear_cursor)]
+	pub type ClearCursor<T> = StorageMap<_, Blake2_128Concat, GroupIndex, Vec<u8>, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		NewMember(T::AccountId),
 		MemberJoinGroup(T::AccountId, GroupIndex, u32),
 		RemoveMember(T::AccountId),
+		/// The group has been scheduled for removal; its scores are cleared over several calls.
+		RemoveGroupStarted(GroupIndex),
+		/// All scores of the group have been removed.
 		RemoveGroup(GroupIndex),
 	}
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		/// Spend leftover block weight on clearing groups scheduled for removal.
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let lookup = T::DbWeight::get().reads(1);
+			if remaining_weight.any_lt(lookup) {
+				return Weight::zero()
+			}
+			let Some(group) = <ClearCursor<T>>::iter_keys().next() else { return lookup };
+
+			let limit = Self::removals_within(remaining_weight.saturating_sub(lookup));
+			if limit == 0 {
+				return lookup
+			}
+			let removed = Self::clear_group_step(group, limit);
+			lookup.saturating_add(Self::clear_weight(removed))
+		}
+
+		fn integrity_test() {
+			// Groups could never be cleared, since every step would remove nothing.
+			assert!(T::MaxRemovalsPerCall::get() > 0, "`MaxRemovalsPerCall` must not be zero");
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+			Self::do_try_state()
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		#[pallet::call_index(0)]
@@ -66,40 +123,81 @@ pub mod pallet {
 			Ok(())
 		}
 
+		/// Join `index` with the given `score`, or update the score if already in the group.
 		#[pallet::call_index(1)]
 		#[pallet::weight(10_000)]
 		pub fn join_group(origin: OriginFor<T>, index: GroupIndex, score: u32) -> DispatchResult {
 			let member = ensure_signed(origin)?;
 			ensure!(Self::is_member(&member), "not a member, can't join group");
+			ensure!(
+				!<ClearCursor<T>>::contains_key(index),
+				"group is being removed, can't join it"
+			);
 			<MemberScore<T>>::insert(&index, &member, score);
-			<GroupMembership<T>>::insert(&member, &index);
+			<GroupMembership<T>>::insert(&member, &index, ());
 
 			Self::deposit_event(Event::MemberJoinGroup(member, index, score));
 			Ok(())
 		}
 
+		/// Leave the pallet entirely, dropping the caller's score in every group they joined.
 		#[pallet::call_index(2)]
 		#[pallet::weight(10_000)]
 		pub fn remove_member(origin: OriginFor<T>) -> DispatchResult {
 			let member_to_remove = ensure_signed(origin)?;
-			ensure!(Self::is_member(&member_to_remove), "not a member, can't remove");
-			let group_id = <GroupMembership<T>>::take(&member_to_remove);
-			<MemberScore<T>>::remove(group_id, &member_to_remove);
+			let mut members = Self::all_members();
+			let position = members
+				.iter()
+				.position(|m| m == &member_to_remove)
+				.ok_or("not a member, can't remove")?;
+
+			for (group_id, _) in <GroupMembership<T>>::drain_prefix(&member_to_remove) {
+				<MemberScore<T>>::remove(group_id, &member_to_remove);
+			}
+			members.swap_remove(position);
+			<AllMembers<T>>::put(members);
 
 			Self::deposit_event(Event::RemoveMember(member_to_remove));
 			Ok(())
 		}
 
+		/// Schedule `group` for removal and clear the first `MaxRemovalsPerCall` scores.
+		///
+		/// Whatever is left is cleared by `continue_clear` or in `on_idle`.
 		#[pallet::call_index(3)]
-		#[pallet::weight(10_000)]
-		pub fn remove_group_score(origin: OriginFor<T>, group: GroupIndex) -> DispatchResult {
+		#[pallet::weight(Pallet::<T>::clear_weight(T::MaxRemovalsPerCall::get()))]
+		pub fn remove_group_score(
+			origin: OriginFor<T>,
+			group: GroupIndex,
+		) -> DispatchResultWithPostInfo {
 			let member = ensure_signed(origin)?;
-			let group_id = <GroupMembership<T>>::get(&member);
-			ensure!(group_id == group, "member isn't in the group, can't remove it");
-			let _ = <MemberScore<T>>::clear_prefix(&group_id, u32::MAX, None);
+			ensure!(
+				<GroupMembership<T>>::contains_key(&member, group),
+				"member isn't in the group, can't remove it"
+			);
+			ensure!(!<ClearCursor<T>>::contains_key(group), "group is already being removed");
 
-			Self::deposit_event(Event::RemoveGroup(group_id));
-			Ok(())
+			<ClearCursor<T>>::insert(group, Vec::<u8>::new());
+			Self::deposit_event(Event::RemoveGroupStarted(group));
+
+			let removed = Self::clear_group_step(group, T::MaxRemovalsPerCall::get());
+			Ok(Some(Self::clear_weight(removed)).into())
+		}
+
+		/// Clear up to `MaxRemovalsPerCall` more scores of a group scheduled for removal.
+		///
+		/// Anyone may call this.
+		#[pallet::call_index(4)]
+		#[pallet::weight(Pallet::<T>::clear_weight(T::MaxRemovalsPerCall::get()))]
+		pub fn continue_clear(
+			origin: OriginFor<T>,
+			group: GroupIndex,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+			ensure!(<ClearCursor<T>>::contains_key(group), "group isn't being removed");
+
+			let removed = Self::clear_group_step(group, T::MaxRemovalsPerCall::get());
+			Ok(Some(Self::clear_weight(removed)).into())
 		}
 	}
 }
@@ -108,4 +206,145 @@ impl<T: Config> Pallet<T> {
 	fn is_member(who: &T::AccountId) -> bool {
 		Self::all_members().contains(who)
 	}
+
+	/// Remove up to `limit` scores of `group`, resuming from its stored cursor.
+	///
+	/// Returns the number of scores removed. Once the group is empty its cursor is dropped and
+	/// `RemoveGroup` is emitted.
+	fn clear_group_step(group: GroupIndex, limit: u32) -> u32 {
+		// Nothing would be removed, so there is no telling whether the group is empty yet.
+		if limit == 0 {
+			return 0
+		}
+		let cursor = <ClearCursor<T>>::get(group).unwrap_or_default();
+		let scores = if cursor.is_empty() {
+			<MemberScore<T>>::iter_prefix(group)
+		} else {
+			<MemberScore<T>>::iter_prefix_from(group, cursor)
+		};
+
+		let mut removed = 0;
+		let mut last_key = None;
+		for (account, _) in scores.take(limit as usize) {
+			// Both indexes have to be cleared together, otherwise members would keep pointing
+			// at a group that no longer holds their score.
+			<MemberScore<T>>::remove(group, &account);
+			<GroupMembership<T>>::remove(&account, group);
+			last_key = Some(<MemberScore<T>>::hashed_key_for(group, &account));
+			removed += 1;
+		}
+
+		match last_key {
+			Some(key) if removed == limit => <ClearCursor<T>>::insert(group, key),
+			_ => {
+				<ClearCursor<T>>::remove(group);
+				Self::deposit_event(Event::RemoveGroup(group));
+			},
+		}
+		removed
+	}
+
+	/// Weight of a clearing step that removed `removed` scores.
+	///
+	/// Each score costs one read to find it and two writes to drop it from both indexes.
+	pub fn clear_weight(removed: u32) -> Weight {
+		let removed = u64::from(removed);
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(2 + removed, 1 + 2 * removed))
+	}
+
+	/// How many scores can be removed without exceeding `budget`.
+	fn removals_within(budget: Weight) -> u32 {
+		let base = Self::clear_weight(0);
+		if budget.any_lt(base) {
+			return 0
+		}
+		let per_removal = Self::clear_weight(1).saturating_sub(base);
+		budget
+			.saturating_sub(base)
+			.checked_div_per_component(&per_removal)
+			.unwrap_or(u64::MAX)
+			.min(T::MaxRemovalsPerCall::get().into()) as u32
+	}
+
+	/// Whether `who` currently holds a score in `group`.
+	pub fn is_in_group(who: &T::AccountId, group: GroupIndex) -> bool {
+		<GroupMembership<T>>::contains_key(who, group)
+	}
+
+	/// The `k` highest scoring members of `group`, best first.
+	///
+	/// Ties are broken by account id so that the result is deterministic.
+	pub fn top_members(group: GroupIndex, k: u32) -> Vec<(T::AccountId, u32)> {
+		let mut members: Vec<_> = <MemberScore<T>>::iter_prefix(group).collect();
+		members.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then_with(|| a.cmp(b)));
+		members.truncate(k as usize);
+		members
+	}
+
+	/// Up to `limit` members of `group` with their scores, in storage order.
+	///
+	/// Iteration resumes right after `start`, so passing the last account of the previous page
+	/// returns the next one. `None` starts from the beginning of the group.
+	pub fn group_members(
+		group: GroupIndex,
+		start: Option<T::AccountId>,
+		limit: u32,
+	) -> Vec<(T::AccountId, u32)> {
+		let iter = match start {
+			Some(account) => <MemberScore<T>>::iter_prefix_from(
+				group,
+				<MemberScore<T>>::hashed_key_for(group, account),
+			),
+			None => <MemberScore<T>>::iter_prefix(group),
+		};
+		iter.take(limit as usize).collect()
+	}
+
+	/// Check that `MemberScore`, `GroupMembership` and `AllMembers` agree with each other.
+	#[cfg(any(feature = "try-runtime", test))]
+	pub fn do_try_state() -> Result<(), sp_runtime::TryRuntimeError> {
+		use frame_support::ensure;
+		use sp_std::collections::btree_set::BTreeSet;
+
+		let members = Self::all_members();
+		let unique: BTreeSet<_> = members.iter().cloned().collect();
+		ensure!(unique.len() == members.len(), "AllMembers contains duplicates");
+
+		for (group, account) in <MemberScore<T>>::iter_keys() {
+			ensure!(
+				<GroupMembership<T>>::contains_key(&account, group),
+				"MemberScore entry without a GroupMembership entry"
+			);
+		}
+
+		for (account, group) in <GroupMembership<T>>::iter_keys() {
+			ensure!(unique.contains(&account), "GroupMembership entry for a non-member");
+			ensure!(
+				<MemberScore<T>>::contains_key(group, &account),
+				"GroupMembership entry without a MemberScore entry"
+			);
+		}
+
+		Ok(())
+	}
+}
+
+/// The members of the group `G`, weighted by their `MemberScore` in it.
+pub struct GroupScores<T, G>(PhantomData<(T, G)>);
+
+impl<T: Config, G: Get<GroupIndex>> WeightedAccountSet for GroupScores<T, G> {
+	type AccountId = T::AccountId;
+	type Weight = u32;
+
+	fn weighted_accounts() -> BTreeMap<Self::AccountId, Self::Weight> {
+		<MemberScore<T>>::iter_prefix(G::get()).collect()
+	}
+
+	fn weight_of(who: &Self::AccountId) -> Option<Self::Weight> {
+		// `MemberScore` is a `ValueQuery`, so a zero score can't tell whether `who` is in the
+		// group.
+		let group = G::get();
+		Pallet::<T>::is_in_group(who, group).then(|| <MemberScore<T>>::get(group, who))
+	}
 }
@@ -1,5 +1,5 @@
 use crate as pallet_double_map;
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::traits::{ConstU16, ConstU32, ConstU64};
 use sp_core::H256;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
@@ -45,6 +45,7 @@ impl frame_system::Config for Test {
 
 impl pallet_double_map::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type MaxRemovalsPerCall = ConstU32<2>;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -1,5 +1,10 @@
-use crate::{mock::*, Event, GroupMembership, MemberScore};
-use frame_support::{assert_err, assert_ok};
+use crate::{mock::*, ClearCursor, Event, GroupMembership, GroupScores, MemberScore};
+use account_set::WeightedAccountSet;
+use frame_support::{
+	assert_err, assert_ok,
+	traits::{ConstU32, Hooks},
+	weights::Weight,
+};
 
 #[test]
 fn join_all_members_works() {
@@ -25,8 +30,30 @@ fn join_group_works() {
 		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(1)));
 		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 5));
 		System::assert_last_event(Event::<Test>::MemberJoinGroup(1, 3, 5).into());
-		assert_eq!(DoubleMap::group_membership(1), 3);
+		assert!(DoubleMap::is_in_group(&1, 3));
 		assert_eq!(DoubleMap::member_score(3, 1), 5);
+		assert_ok!(DoubleMap::do_try_state());
+	})
+}
+
+#[test]
+fn join_multiple_groups_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(1)));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 5));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 4, 7));
+
+		// joining a second group keeps the first one
+		assert!(DoubleMap::is_in_group(&1, 3));
+		assert!(DoubleMap::is_in_group(&1, 4));
+		assert_eq!(DoubleMap::member_score(3, 1), 5);
+		assert_eq!(DoubleMap::member_score(4, 1), 7);
+
+		// re-joining a group only updates the score
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 9));
+		assert_eq!(DoubleMap::member_score(3, 1), 9);
+		assert_eq!(<GroupMembership<Test>>::iter_prefix(1).count(), 2);
+		assert_ok!(DoubleMap::do_try_state());
 	})
 }
 
@@ -35,10 +62,20 @@ fn remove_member_works() {
 	new_test_ext().execute_with(|| {
 		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(1)));
 		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 5));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 4, 6));
 		assert_ok!(DoubleMap::remove_member(RuntimeOrigin::signed(1)));
 		System::assert_last_event(Event::<Test>::RemoveMember(1).into());
-		assert!(!<GroupMembership<Test>>::contains_key(1));
+		assert!(!<GroupMembership<Test>>::contains_key(1, 3));
+		assert!(!<GroupMembership<Test>>::contains_key(1, 4));
 		assert!(!<MemberScore<Test>>::contains_key(3, 1));
+		assert!(!<MemberScore<Test>>::contains_key(4, 1));
+		assert_eq!(DoubleMap::all_members(), Vec::<u64>::new());
+		assert_ok!(DoubleMap::do_try_state());
+
+		assert_err!(
+			DoubleMap::remove_member(RuntimeOrigin::signed(1)),
+			"not a member, can't remove"
+		);
 	})
 }
 
@@ -64,11 +101,199 @@ fn remove_group_score_works() {
 
 		assert_ok!(DoubleMap::remove_group_score(RuntimeOrigin::signed(1), 3));
 
+		// only `MaxRemovalsPerCall` scores are removed right away
+		System::assert_last_event(Event::<Test>::RemoveGroupStarted(3).into());
+		assert_eq!(<MemberScore<Test>>::iter_prefix(3).count(), 1);
+		assert!(DoubleMap::clear_cursor(3).is_some());
+		assert_ok!(DoubleMap::do_try_state());
+
+		// anyone can finish the job
+		assert_ok!(DoubleMap::continue_clear(RuntimeOrigin::signed(4), 3));
 		System::assert_last_event(Event::<Test>::RemoveGroup(3).into());
+		assert_eq!(DoubleMap::clear_cursor(3), None);
 
 		// check: user 1, 2, 3 should no longer in the group
 		assert!(!<MemberScore<Test>>::contains_key(3, 1));
 		assert!(!<MemberScore<Test>>::contains_key(3, 2));
 		assert!(!<MemberScore<Test>>::contains_key(3, 3));
+		// ...and the reverse index no longer lists the group for any of them
+		assert!(!DoubleMap::is_in_group(&1, 3));
+		assert!(!DoubleMap::is_in_group(&2, 3));
+		assert!(!DoubleMap::is_in_group(&3, 3));
+		assert_ok!(DoubleMap::do_try_state());
+	})
+}
+
+#[test]
+fn empty_clear_step_keeps_the_group() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(1)));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 5));
+		<ClearCursor<Test>>::insert(3, Vec::<u8>::new());
+		System::reset_events();
+
+		assert_eq!(DoubleMap::clear_group_step(3, 0), 0);
+		assert!(DoubleMap::clear_cursor(3).is_some());
+		assert_eq!(DoubleMap::member_score(3, 1), 5);
+		assert!(System::events().is_empty());
+	})
+}
+
+#[test]
+fn remove_group_score_keeps_other_groups() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(1)));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 5));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 4, 6));
+
+		assert_ok!(DoubleMap::remove_group_score(RuntimeOrigin::signed(1), 3));
+
+		assert!(!DoubleMap::is_in_group(&1, 3));
+		assert!(DoubleMap::is_in_group(&1, 4));
+		assert_eq!(DoubleMap::member_score(4, 1), 6);
+		assert_ok!(DoubleMap::do_try_state());
+	})
+}
+
+#[test]
+fn try_state_detects_inconsistent_indexes() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(1)));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 3, 5));
+
+		<GroupMembership<Test>>::remove(1, 3);
+		assert!(DoubleMap::do_try_state().is_err());
+	})
+}
+
+#[test]
+fn top_members_works() {
+	new_test_ext().execute_with(|| {
+		for (who, score) in [(1, 5), (2, 9), (3, 1), (4, 9)] {
+			assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(who)));
+			assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(who), 3, score));
+		}
+		// a member of another group never shows up
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(3), 4, 100));
+
+		assert_eq!(DoubleMap::top_members(3, 3), vec![(2, 9), (4, 9), (1, 5)]);
+		assert_eq!(DoubleMap::top_members(3, 10).len(), 4);
+		assert_eq!(DoubleMap::top_members(4, 10), vec![(3, 100)]);
+		assert!(DoubleMap::top_members(5, 10).is_empty());
+	})
+}
+
+#[test]
+fn group_members_paginates() {
+	new_test_ext().execute_with(|| {
+		for who in 1..=5 {
+			assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(who)));
+			assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(who), 3, who as u32));
+		}
+
+		let mut pages = Vec::new();
+		let mut start = None;
+		loop {
+			let page = DoubleMap::group_members(3, start, 2);
+			if page.is_empty() {
+				break
+			}
+			assert!(page.len() <= 2);
+			start = page.last().map(|(who, _)| *who);
+			pages.extend(page);
+		}
+
+		let mut all: Vec<_> = <MemberScore<Test>>::iter_prefix(3).collect();
+		assert_eq!(pages, all);
+		all.sort();
+		assert_eq!(all, vec![(1, 1), (2, 2), (3, 3), (4, 4), (5, 5)]);
+	})
+}
+
+#[test]
+fn continue_clear_handles_errors() {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DoubleMap::continue_clear(RuntimeOrigin::signed(1), 3).map_err(|e| e.error),
+			"group isn't being removed"
+		);
+
+		for who in 1..=3 {
+			assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(who)));
+			assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(who), 3, 5));
+		}
+		assert_ok!(DoubleMap::remove_group_score(RuntimeOrigin::signed(3), 3));
+
+		let (remaining, _) = <MemberScore<Test>>::iter_prefix(3).next().unwrap();
+		assert_err!(
+			DoubleMap::remove_group_score(RuntimeOrigin::signed(remaining), 3).map_err(|e| e.error),
+			"group is already being removed"
+		);
+
+		assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(4)));
+
+		assert_err!(
+			DoubleMap::join_group(RuntimeOrigin::signed(4), 3, 5),
+			"group is being removed, can't join it"
+		);
+	})
+}
+
+#[test]
+fn clearing_charges_actual_weight() {
+	new_test_ext().execute_with(|| {
+		for who in 1..=3 {
+			assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(who)));
+			assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(who), 3, 5));
+		}
+
+		let info = DoubleMap::remove_group_score(RuntimeOrigin::signed(1), 3).unwrap();
+		assert_eq!(info.actual_weight, Some(DoubleMap::clear_weight(2)));
+
+		let info = DoubleMap::continue_clear(RuntimeOrigin::signed(1), 3).unwrap();
+		assert_eq!(info.actual_weight, Some(DoubleMap::clear_weight(1)));
+	})
+}
+
+#[test]
+fn on_idle_clears_groups() {
+	new_test_ext().execute_with(|| {
+		for who in 1..=5 {
+			assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(who)));
+			assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(who), 3, 5));
+		}
+		assert_ok!(DoubleMap::remove_group_score(RuntimeOrigin::signed(1), 3));
+		assert_eq!(<MemberScore<Test>>::iter_prefix(3).count(), 3);
+
+		// no weight left, nothing happens
+		assert_eq!(DoubleMap::on_idle(1, Weight::zero()), Weight::zero());
+		assert_eq!(<MemberScore<Test>>::iter_prefix(3).count(), 3);
+
+		DoubleMap::on_idle(1, Weight::MAX);
+		assert_eq!(<MemberScore<Test>>::iter_prefix(3).count(), 1);
+
+		DoubleMap::on_idle(2, Weight::MAX);
+		System::assert_last_event(Event::<Test>::RemoveGroup(3).into());
+		assert_eq!(DoubleMap::clear_cursor(3), None);
+		assert_eq!(<GroupMembership<Test>>::iter().count(), 0);
+		assert_ok!(DoubleMap::do_try_state());
+	})
+}
+
+#[test]
+fn group_scores_weight_members_by_score() {
+	new_test_ext().execute_with(|| {
+		type Scores = GroupScores<Test, ConstU32<1>>;
+		for who in 1..=3 {
+			assert_ok!(DoubleMap::join_all_members(RuntimeOrigin::signed(who)));
+		}
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(1), 1, 10));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(2), 1, 0));
+		assert_ok!(DoubleMap::join_group(RuntimeOrigin::signed(3), 2, 30));
+
+		assert_eq!(Scores::weighted_accounts(), [(1, 10), (2, 0)].into_iter().collect());
+		assert_eq!(Scores::weight_of(&2), Some(0));
+		assert_eq!(Scores::weight_of(&3), None);
+		assert_eq!(Scores::total_weight(), 10);
 	})
 }
@@ -0,0 +1,15 @@
+//! Runtime API definition for the fixed-point pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use fixed_point::DriftReport;
+use sp_arithmetic::Permill;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait FixedPointApi {
+		/// Multiply one by each of `factors` with every accumulator implementation and report how
+		/// far each result drifts from the exact product.
+		fn compare_drift(factors: Vec<Permill>) -> DriftReport;
+	}
+}
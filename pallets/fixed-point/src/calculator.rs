@@ -0,0 +1,372 @@
+//! Fixed-point arithmetic over a choice of numeric representations.
+//!
+//! [`FixedValue`] tags a number with its representation so that values of different types can be
+//! stored side by side, and [`FixedValue::apply`] runs an [`Operation`] on it with either checked
+//! or saturating overflow handling. Nothing here touches storage, so other pallets can use it
+//! directly.
+
+use codec::{Decode, Encode};
+use frame_support::RuntimeDebug;
+use scale_info::TypeInfo;
+use sp_arithmetic::{
+	traits::{Bounded, CheckedAdd, CheckedDiv, CheckedMul, One, Saturating, Zero},
+	FixedU128, PerThing, Perbill, Permill,
+};
+use substrate_fixed::types::{I64F64, U16F16, U32F32};
+
+/// A number in one of the supported fixed-point representations.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+pub enum FixedValue {
+	Permill(Permill),
+	Perbill(Perbill),
+	FixedU128(FixedU128),
+	U16F16(U16F16),
+	U32F32(U32F32),
+	I64F64(I64F64),
+}
+
+/// An operation to apply to a [`FixedValue`].
+///
+/// Operands must use the same representation as the value they are applied to.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+pub enum Operation {
+	Mul(FixedValue),
+	Div(FixedValue),
+	Add(FixedValue),
+	Pow(u32),
+}
+
+/// What to do when a result does not fit in the representation.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+pub enum OverflowMode {
+	/// Fail with [`CalcError::Overflow`].
+	Checked,
+	/// Clamp the result to the closest representable value.
+	Saturating,
+}
+
+/// Why an operation could not be carried out.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum CalcError {
+	/// The result does not fit in the representation.
+	Overflow,
+	/// Division by zero, in either overflow mode.
+	DivisionByZero,
+	/// The operand uses a different representation than the value.
+	RepresentationMismatch,
+}
+
+impl FixedValue {
+	/// Apply `operation` to this value, keeping its representation.
+	pub fn apply(self, operation: Operation, mode: OverflowMode) -> Result<Self, CalcError> {
+		macro_rules! apply_as {
+			($variant:ident, $value:expr) => {{
+				let operand = |o: FixedValue| match o {
+					FixedValue::$variant(x) => Ok(x),
+					_ => Err(CalcError::RepresentationMismatch),
+				};
+				let op = match operation {
+					Operation::Mul(o) => Op::Mul(operand(o)?),
+					Operation::Div(o) => Op::Div(operand(o)?),
+					Operation::Add(o) => Op::Add(operand(o)?),
+					Operation::Pow(exp) => Op::Pow(exp),
+				};
+				calculate($value, op, mode).map(FixedValue::$variant)
+			}};
+		}
+
+		match self {
+			FixedValue::Permill(v) => apply_as!(Permill, v),
+			FixedValue::Perbill(v) => apply_as!(Perbill, v),
+			FixedValue::FixedU128(v) => apply_as!(FixedU128, v),
+			FixedValue::U16F16(v) => apply_as!(U16F16, v),
+			FixedValue::U32F32(v) => apply_as!(U32F32, v),
+			FixedValue::I64F64(v) => apply_as!(I64F64, v),
+		}
+	}
+}
+
+/// [`Operation`] with the operand unwrapped to the concrete representation.
+enum Op<N> {
+	Mul(N),
+	Div(N),
+	Add(N),
+	Pow(u32),
+}
+
+fn calculate<N: FixedArithmetic>(value: N, op: Op<N>, mode: OverflowMode) -> Result<N, CalcError> {
+	match op {
+		Op::Mul(rhs) => value.mul_with(rhs, mode),
+		Op::Div(rhs) => {
+			if rhs == N::zero() {
+				return Err(CalcError::DivisionByZero)
+			}
+			value.div_with(rhs, mode)
+		},
+		Op::Add(rhs) => value.add_with(rhs, mode),
+		Op::Pow(exp) => pow_with(value, exp, mode),
+	}
+	.ok_or(CalcError::Overflow)
+}
+
+/// Exponentiation by squaring on top of `mul_with`.
+fn pow_with<N: FixedArithmetic>(mut base: N, mut exp: u32, mode: OverflowMode) -> Option<N> {
+	let mut result = N::one();
+	while exp > 0 {
+		if exp & 1 == 1 {
+			result = result.mul_with(base, mode)?;
+		}
+		exp >>= 1;
+		if exp > 0 {
+			base = base.mul_with(base, mode)?;
+		}
+	}
+	Some(result)
+}
+
+/// The common interface over the supported representations.
+///
+/// Every method returns `None` on overflow in [`OverflowMode::Checked`], and always returns a
+/// value in [`OverflowMode::Saturating`]. Division by zero is rejected before `div_with` is called.
+trait FixedArithmetic: Copy + PartialOrd {
+	fn zero() -> Self;
+	fn one() -> Self;
+	fn mul_with(self, rhs: Self, mode: OverflowMode) -> Option<Self>;
+	fn div_with(self, rhs: Self, mode: OverflowMode) -> Option<Self>;
+	fn add_with(self, rhs: Self, mode: OverflowMode) -> Option<Self>;
+}
+
+macro_rules! impl_per_thing {
+	($type:ty) => {
+		impl FixedArithmetic for $type {
+			fn zero() -> Self {
+				<$type as PerThing>::zero()
+			}
+
+			fn one() -> Self {
+				<$type as PerThing>::one()
+			}
+
+			fn mul_with(self, rhs: Self, _mode: OverflowMode) -> Option<Self> {
+				// The product of two values in [0, 1] is still in [0, 1].
+				Some(Saturating::saturating_mul(self, rhs))
+			}
+
+			fn div_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+				if self > rhs {
+					return match mode {
+						OverflowMode::Checked => None,
+						OverflowMode::Saturating => Some(<$type as PerThing>::one()),
+					}
+				}
+				Some(<$type>::from_rational(self.deconstruct(), rhs.deconstruct()))
+			}
+
+			fn add_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+				match mode {
+					OverflowMode::Checked => self
+						.deconstruct()
+						.checked_add(rhs.deconstruct())
+						.filter(|parts| *parts <= <$type>::ACCURACY)
+						.map(<$type>::from_parts),
+					OverflowMode::Saturating => Some(Saturating::saturating_add(self, rhs)),
+				}
+			}
+		}
+	};
+}
+
+impl_per_thing!(Permill);
+impl_per_thing!(Perbill);
+
+impl FixedArithmetic for FixedU128 {
+	fn zero() -> Self {
+		<FixedU128 as Zero>::zero()
+	}
+
+	fn one() -> Self {
+		<FixedU128 as One>::one()
+	}
+
+	fn mul_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+		match mode {
+			OverflowMode::Checked => CheckedMul::checked_mul(&self, &rhs),
+			OverflowMode::Saturating => Some(Saturating::saturating_mul(self, rhs)),
+		}
+	}
+
+	fn div_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+		match mode {
+			OverflowMode::Checked => CheckedDiv::checked_div(&self, &rhs),
+			// Unsigned, so a quotient can only overflow upwards.
+			OverflowMode::Saturating =>
+				Some(CheckedDiv::checked_div(&self, &rhs).unwrap_or_else(Bounded::max_value)),
+		}
+	}
+
+	fn add_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+		match mode {
+			OverflowMode::Checked => CheckedAdd::checked_add(&self, &rhs),
+			OverflowMode::Saturating => Some(Saturating::saturating_add(self, rhs)),
+		}
+	}
+}
+
+macro_rules! impl_substrate_fixed {
+	($type:ty) => {
+		impl FixedArithmetic for $type {
+			fn zero() -> Self {
+				<$type>::from_num(0)
+			}
+
+			fn one() -> Self {
+				<$type>::from_num(1)
+			}
+
+			fn mul_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+				match mode {
+					OverflowMode::Checked => self.checked_mul(rhs),
+					OverflowMode::Saturating => Some(self.saturating_mul(rhs)),
+				}
+			}
+
+			fn div_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+				let zero = <$type as FixedArithmetic>::zero();
+				match mode {
+					OverflowMode::Checked => self.checked_div(rhs),
+					OverflowMode::Saturating => Some(self.checked_div(rhs).unwrap_or_else(|| {
+						if (self < zero) != (rhs < zero) {
+							<$type>::min_value()
+						} else {
+							<$type>::max_value()
+						}
+					})),
+				}
+			}
+
+			fn add_with(self, rhs: Self, mode: OverflowMode) -> Option<Self> {
+				match mode {
+					OverflowMode::Checked => self.checked_add(rhs),
+					OverflowMode::Saturating => Some(self.saturating_add(rhs)),
+				}
+			}
+		}
+	};
+}
+
+impl_substrate_fixed!(U16F16);
+impl_substrate_fixed!(U32F32);
+impl_substrate_fixed!(I64F64);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_arithmetic::FixedPointNumber;
+
+	use OverflowMode::{Checked, Saturating as Sat};
+
+	#[test]
+	fn per_thing_arithmetic() {
+		let half = FixedValue::Permill(Permill::from_percent(50));
+		let quarter = FixedValue::Permill(Permill::from_percent(25));
+
+		assert_eq!(half.apply(Operation::Mul(half), Checked), Ok(quarter));
+		assert_eq!(quarter.apply(Operation::Div(half), Checked), Ok(half));
+		assert_eq!(quarter.apply(Operation::Add(quarter), Checked), Ok(half));
+		assert_eq!(half.apply(Operation::Pow(2), Checked), Ok(quarter));
+		assert_eq!(half.apply(Operation::Pow(0), Checked), Ok(FixedValue::Permill(Permill::one())));
+
+		// results above one
+		assert_eq!(half.apply(Operation::Div(quarter), Checked), Err(CalcError::Overflow));
+		assert_eq!(
+			half.apply(Operation::Div(quarter), Sat),
+			Ok(FixedValue::Permill(Permill::one()))
+		);
+		let most = FixedValue::Perbill(Perbill::from_percent(90));
+		assert_eq!(most.apply(Operation::Add(most), Checked), Err(CalcError::Overflow));
+		assert_eq!(most.apply(Operation::Add(most), Sat), Ok(FixedValue::Perbill(Perbill::one())));
+	}
+
+	#[test]
+	fn fixed_u128_arithmetic() {
+		let two = FixedValue::FixedU128(FixedU128::saturating_from_integer(2u32));
+		let eight = FixedValue::FixedU128(FixedU128::saturating_from_integer(8u32));
+		let max = FixedValue::FixedU128(<FixedU128 as Bounded>::max_value());
+
+		assert_eq!(two.apply(Operation::Pow(3), Checked), Ok(eight));
+		assert_eq!(
+			eight.apply(Operation::Div(two), Checked),
+			two.apply(Operation::Pow(2), Checked)
+		);
+		assert_eq!(max.apply(Operation::Mul(two), Checked), Err(CalcError::Overflow));
+		assert_eq!(max.apply(Operation::Mul(two), Sat), Ok(max));
+		assert_eq!(max.apply(Operation::Add(two), Sat), Ok(max));
+	}
+
+	#[test]
+	fn substrate_fixed_arithmetic() {
+		let half = FixedValue::U16F16(U16F16::from_num(1) / 2);
+		let three = FixedValue::U16F16(U16F16::from_num(3));
+		assert_eq!(
+			three.apply(Operation::Mul(half), Checked),
+			Ok(FixedValue::U16F16(U16F16::from_num(3) / 2))
+		);
+		assert_eq!(
+			three.apply(Operation::Add(half), Checked),
+			Ok(FixedValue::U16F16(U16F16::from_num(7) / 2))
+		);
+		assert_eq!(
+			three.apply(Operation::Div(half), Checked),
+			Ok(FixedValue::U16F16(U16F16::from_num(6)))
+		);
+		assert_eq!(
+			three.apply(Operation::Pow(4), Checked),
+			Ok(FixedValue::U16F16(U16F16::from_num(81)))
+		);
+
+		let big = FixedValue::U32F32(U32F32::from_num(1u32 << 20));
+		assert_eq!(big.apply(Operation::Pow(2), Checked), Err(CalcError::Overflow));
+		assert_eq!(big.apply(Operation::Pow(2), Sat), Ok(FixedValue::U32F32(U32F32::max_value())));
+
+		let minus_big = FixedValue::I64F64(I64F64::from_num(-(1i64 << 40)));
+		let tiny = FixedValue::I64F64(I64F64::from_num(1) / (1 << 30));
+		assert_eq!(minus_big.apply(Operation::Div(tiny), Checked), Err(CalcError::Overflow));
+		assert_eq!(
+			minus_big.apply(Operation::Div(tiny), Sat),
+			Ok(FixedValue::I64F64(I64F64::min_value()))
+		);
+		assert_eq!(
+			minus_big.apply(Operation::Pow(3), Sat),
+			Ok(FixedValue::I64F64(I64F64::min_value()))
+		);
+	}
+
+	#[test]
+	fn division_by_zero_is_rejected() {
+		let zeros = [
+			FixedValue::Permill(Permill::zero()),
+			FixedValue::Perbill(Perbill::zero()),
+			FixedValue::FixedU128(<FixedU128 as Zero>::zero()),
+			FixedValue::U16F16(U16F16::from_num(0)),
+			FixedValue::U32F32(U32F32::from_num(0)),
+			FixedValue::I64F64(I64F64::from_num(0)),
+		];
+		for zero in zeros {
+			for mode in [Checked, Sat] {
+				assert_eq!(zero.apply(Operation::Div(zero), mode), Err(CalcError::DivisionByZero));
+			}
+		}
+	}
+
+	#[test]
+	fn mismatched_representations_are_rejected() {
+		let permill = FixedValue::Permill(Permill::one());
+		let perbill = FixedValue::Perbill(Perbill::one());
+		assert_eq!(
+			permill.apply(Operation::Mul(perbill), Checked),
+			Err(CalcError::RepresentationMismatch)
+		);
+		// `Pow` has no operand, so any representation works
+		assert_eq!(permill.apply(Operation::Pow(5), Checked), Ok(permill));
+	}
+}
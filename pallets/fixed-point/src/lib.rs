@@ -1,6 +1,23 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use codec::{Decode, Encode};
+use frame_support::RuntimeDebug;
+use q16::Rounding;
+use scale_info::TypeInfo;
+use sp_arithmetic::{
+	traits::{One, Saturating},
+	FixedPointNumber, FixedU128, PerThing, Permill,
+};
+use sp_std::vec::Vec;
+use substrate_fixed::types::U16F16;
+
+pub use calculator::{CalcError, FixedValue, Operation, OverflowMode};
 pub use pallet::*;
+pub use q16::Q16;
+
+pub mod calculator;
+pub mod migrations;
+pub mod q16;
 
 #[cfg(test)]
 mod mock;
@@ -10,17 +27,34 @@ mod tests;
 
 #[frame_support::pallet(dev_mode)]
 pub mod pallet {
+	use crate::{q16::Rounding, FixedValue, Operation, OverflowMode, Q16};
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 	use sp_arithmetic::{traits::Saturating, Permill};
 	use substrate_fixed::types::U16F16;
 
+	/// The current storage version.
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The maximum length of a snapshot name.
+		#[pallet::constant]
+		type MaxNameLength: Get<u32>;
+
+		/// The maximum number of snapshots an account can keep at once.
+		#[pallet::constant]
+		type MaxSnapshots: Get<u32>;
+
+		/// The maximum number of accumulators an account can own at once.
+		#[pallet::constant]
+		type MaxAccumulators: Get<u32>;
 	}
 
 	#[pallet::type_value]
@@ -30,8 +64,14 @@ pub mod pallet {
 
 	#[pallet::storage]
 	#[pallet::getter(fn permil_value)]
-	pub type PermilAccumulator<T: Config> =
-		StorageValue<_, Permill, ValueQuery, PermillAccumulatorDefaultValue<T>>;
+	pub type PermilAccumulator<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Permill,
+		ValueQuery,
+		PermillAccumulatorDefaultValue<T>,
+	>;
 
 	#[pallet::type_value]
 	pub fn FixedAccumulatorDefaultValue<T: Config>() -> U16F16 {
@@ -40,8 +80,14 @@ pub mod pallet {
 
 	#[pallet::storage]
 	#[pallet::getter(fn fixed_value)]
-	pub type FixedAccumulator<T: Config> =
-		StorageValue<_, U16F16, ValueQuery, FixedAccumulatorDefaultValue<T>>;
+	pub type FixedAccumulator<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		U16F16,
+		ValueQuery,
+		FixedAccumulatorDefaultValue<T>,
+	>;
 
 	#[pallet::type_value]
 	pub fn ManualAccumulatorDefaultValue<T: Config>() -> u32 {
@@ -50,25 +96,124 @@ pub mod pallet {
 
 	#[pallet::storage]
 	#[pallet::getter(fn manual_value)]
-	pub type ManualAccumulator<T: Config> =
-		StorageValue<_, u32, ValueQuery, ManualAccumulatorDefaultValue<T>>;
+	pub type ManualAccumulator<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		u32,
+		ValueQuery,
+		ManualAccumulatorDefaultValue<T>,
+	>;
+
+	/// The name under which a snapshot is stored.
+	pub type SnapshotName<T> = BoundedVec<u8, <T as Config>::MaxNameLength>;
+
+	/// The values of an account's three accumulators at some point in time.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+	pub struct Checkpoint {
+		pub permill: Permill,
+		pub fixed: U16F16,
+		pub manual: u32,
+	}
+
+	#[pallet::storage]
+	#[pallet::getter(fn snapshots)]
+	pub type Snapshots<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		SnapshotName<T>,
+		Checkpoint,
+		OptionQuery,
+	>;
+
+	/// Number of snapshots each account currently keeps, bounded by `MaxSnapshots`.
+	#[pallet::storage]
+	#[pallet::getter(fn snapshot_count)]
+	pub type SnapshotCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// Identifier of an accumulator created with `create_accumulator`.
+	pub type AccumulatorId = u32;
+
+	/// An accumulator created on demand, in the representation of its `value`.
+	#[derive(Encode, Decode, Clone, PartialEq, TypeInfo, RuntimeDebug)]
+	pub struct Accumulator<AccountId> {
+		/// The only account allowed to update or remove the accumulator
+		pub owner: AccountId,
+		/// The current value
+		pub value: FixedValue,
+	}
+
+	#[pallet::storage]
+	#[pallet::getter(fn accumulators)]
+	pub type Accumulators<T: Config> =
+		StorageMap<_, Blake2_128Concat, AccumulatorId, Accumulator<T::AccountId>, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn accumulator_count)]
+	pub type AccumulatorCount<T> = StorageValue<_, AccumulatorId, ValueQuery>;
+
+	/// Number of accumulators each account currently owns, bounded by `MaxAccumulators`.
+	#[pallet::storage]
+	#[pallet::getter(fn owned_accumulators)]
+	pub type OwnedAccumulators<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub (super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		// For all varients of the event, the contained data is
-		// (new_factor, new_product)
+		// (who, new_factor, new_product)
 		/// Permill accumulator has been updated.
-		PermillUpdated(Permill, Permill),
+		PermillUpdated(T::AccountId, Permill, Permill),
 		/// Substrate-fixed accumulator has been updated.
-		FixedUpdated(U16F16, U16F16),
+		FixedUpdated(T::AccountId, U16F16, U16F16),
 		/// Manual accumulator has been updated.
-		ManualUpdated(u32, u32),
+		ManualUpdated(T::AccountId, u32, u32),
+		/// An account's accumulators have been reset, either to one or to a snapshot.
+		/// [who, snapshot]
+		AccumulatorsReset(T::AccountId, Option<SnapshotName<T>>),
+		/// An account's accumulators have been saved. [who, name, checkpoint]
+		SnapshotTaken(T::AccountId, SnapshotName<T>, Checkpoint),
+		/// A snapshot has been removed. [who, name]
+		SnapshotRemoved(T::AccountId, SnapshotName<T>),
+		/// A generic accumulator has been created. [id, owner, initial_value]
+		AccumulatorCreated(AccumulatorId, T::AccountId, FixedValue),
+		/// A generic accumulator has been updated. [id, operation, new_value]
+		AccumulatorUpdated(AccumulatorId, Operation, FixedValue),
+		/// A generic accumulator has been removed.
+		AccumulatorRemoved(AccumulatorId),
 	}
 
 	#[pallet::error]
 	pub enum Error<T> {
 		Overflow,
+		/// Attempted to divide by zero
+		DivisionByZero,
+		/// The operand's representation differs from the accumulator's
+		RepresentationMismatch,
+		/// No accumulator exists with the given id
+		UnknownAccumulator,
+		/// Only the owner may modify an accumulator
+		NotOwner,
+		/// The account has no snapshot with the given name
+		UnknownSnapshot,
+		/// The account already keeps `MaxSnapshots` snapshots
+		TooManySnapshots,
+		/// The account already owns `MaxAccumulators` accumulators
+		TooManyAccumulators,
+	}
+
+	impl<T> From<crate::CalcError> for Error<T> {
+		fn from(error: crate::CalcError) -> Self {
+			match error {
+				crate::CalcError::Overflow => Error::Overflow,
+				crate::CalcError::DivisionByZero => Error::DivisionByZero,
+				crate::CalcError::RepresentationMismatch => Error::RepresentationMismatch,
+			}
+		}
 	}
 
 	#[pallet::call]
@@ -78,17 +223,17 @@ pub mod pallet {
 		#[pallet::call_index(0)]
 		#[pallet::weight(10_000)]
 		pub fn update_permil(origin: OriginFor<T>, new_factor: Permill) -> DispatchResult {
-			ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
 
-			let old_accumulated = Self::permil_value();
+			let old_accumulated = Self::permil_value(&who);
 			// There is no need to check for overflow here. Permill holds values in the range
 			// [0, 1] so it is impossible to ever overflow.
 			let new_product = old_accumulated.saturating_mul(new_factor);
 
 			// Write the new value to storage
-			PermilAccumulator::<T>::put(new_product);
+			PermilAccumulator::<T>::insert(&who, new_product);
 
-			Self::deposit_event(Event::PermillUpdated(new_factor, new_product));
+			Self::deposit_event(Event::PermillUpdated(who, new_factor, new_product));
 
 			Ok(())
 		}
@@ -98,17 +243,17 @@ pub mod pallet {
 		#[pallet::call_index(1)]
 		#[pallet::weight(10_000)]
 		pub fn update_fixed(origin: OriginFor<T>, new_factor: U16F16) -> DispatchResult {
-			ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
 
-			let old_accumulated = Self::fixed_value();
+			let old_accumulated = Self::fixed_value(&who);
 
 			// Multiply, handling overflow
 			let new_product =
 				old_accumulated.checked_mul(new_factor).ok_or(Error::<T>::Overflow)?;
 
-			FixedAccumulator::<T>::put(new_product);
+			FixedAccumulator::<T>::insert(&who, new_product);
 
-			Self::deposit_event(Event::FixedUpdated(new_factor, new_product));
+			Self::deposit_event(Event::FixedUpdated(who, new_factor, new_product));
 
 			Ok(())
 		}
@@ -118,36 +263,221 @@ pub mod pallet {
 		#[pallet::call_index(2)]
 		#[pallet::weight(10_000)]
 		pub fn update_manual(origin: OriginFor<T>, new_factor: u32) -> DispatchResult {
-			ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
+
+			// The stored bits are interpreted as Q16.16, which takes care of widening the
+			// product and shifting it back down. This is a lossy conversion, the bits below
+			// 2^-16 are truncated.
+			let old_accumulated = Q16::from_bits(Self::manual_value(&who));
+			let final_product = old_accumulated
+				.checked_mul(Q16::from_bits(new_factor), Rounding::Down)
+				.ok_or(Error::<T>::Overflow)?
+				.to_bits();
+
+			// Write the new value to storage
+			ManualAccumulator::<T>::insert(&who, final_product);
+
+			// Emit event
+			Self::deposit_event(Event::ManualUpdated(who, new_factor, final_product));
+
+			Ok(())
+		}
+
+		/// Create a new accumulator holding `initial`. Its representation is fixed from then on.
+		///
+		/// An account may own at most `MaxAccumulators` accumulators at once.
+		#[pallet::call_index(3)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(2, 3)))]
+		pub fn create_accumulator(origin: OriginFor<T>, initial: FixedValue) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+
+			let id = Self::accumulator_count();
+			let next_id = id.checked_add(1).ok_or(Error::<T>::Overflow)?;
+			OwnedAccumulators::<T>::try_mutate(&owner, |count| -> DispatchResult {
+				ensure!(*count < T::MaxAccumulators::get(), Error::<T>::TooManyAccumulators);
+				*count += 1;
+				Ok(())
+			})?;
+
+			Accumulators::<T>::insert(id, Accumulator { owner: owner.clone(), value: initial });
+			AccumulatorCount::<T>::put(next_id);
+
+			Self::deposit_event(Event::AccumulatorCreated(id, owner, initial));
+
+			Ok(())
+		}
+
+		/// Apply `operation` to one of the caller's accumulators.
+		#[pallet::call_index(4)]
+		#[pallet::weight(10_000)]
+		pub fn update_accumulator(
+			origin: OriginFor<T>,
+			id: AccumulatorId,
+			operation: Operation,
+			mode: OverflowMode,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
 
-			// To ensure we don't overflow unnecessarily, the values are cast up to u64 before
-			// multiplying. This intermediate format has 48 integer positions and 16 fractional.
-			let old_accumulated: u64 = Self::manual_value().into();
-			let new_factor_u64: u64 = new_factor.into();
+			let mut accumulator = Self::accumulators(id).ok_or(Error::<T>::UnknownAccumulator)?;
+			ensure!(accumulator.owner == who, Error::<T>::NotOwner);
 
-			// Perform the multiplication on the u64 values
-			// This intermediate format has 32 integer positions and 32 fractional.
-			let raw_product: u64 = old_accumulated * new_factor_u64;
+			accumulator.value =
+				accumulator.value.apply(operation, mode).map_err(Error::<T>::from)?;
+			Accumulators::<T>::insert(id, &accumulator);
 
-			// Right shift to restore the convention that 16 bits are fractional.
-			// This is a lossy conversion.
-			// This intermediate format has 48 integer positions and 16 fractional.
-			let shifted_product: u64 = raw_product << 16;
+			Self::deposit_event(Event::AccumulatorUpdated(id, operation, accumulator.value));
 
-			// Ensure that the product fits in the u32, and error if it doesn't
-			if shifted_product > (u32::MAX as u64) {
-				return Err(Error::<T>::Overflow.into());
+			Ok(())
+		}
+
+		/// Remove one of the caller's accumulators, making room for another.
+		#[pallet::call_index(5)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(2, 2)))]
+		pub fn remove_accumulator(origin: OriginFor<T>, id: AccumulatorId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let accumulator = Self::accumulators(id).ok_or(Error::<T>::UnknownAccumulator)?;
+			ensure!(accumulator.owner == who, Error::<T>::NotOwner);
+
+			Accumulators::<T>::remove(id);
+			OwnedAccumulators::<T>::mutate_exists(&who, |count| {
+				*count = count.map(|c| c.saturating_sub(1)).filter(|c| *c > 0)
+			});
+
+			Self::deposit_event(Event::AccumulatorRemoved(id));
+
+			Ok(())
+		}
+
+		/// Reset the caller's three accumulators, to one if `to` is `None` or to the values
+		/// saved in the named snapshot otherwise.
+		#[pallet::call_index(6)]
+		#[pallet::weight(10_000)]
+		pub fn reset(origin: OriginFor<T>, to: Option<SnapshotName<T>>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			match &to {
+				Some(name) => {
+					let checkpoint =
+						Self::snapshots(&who, name).ok_or(Error::<T>::UnknownSnapshot)?;
+					PermilAccumulator::<T>::insert(&who, checkpoint.permill);
+					FixedAccumulator::<T>::insert(&who, checkpoint.fixed);
+					ManualAccumulator::<T>::insert(&who, checkpoint.manual);
+				},
+				None => {
+					// Removing the entries brings back the default values.
+					PermilAccumulator::<T>::remove(&who);
+					FixedAccumulator::<T>::remove(&who);
+					ManualAccumulator::<T>::remove(&who);
+				},
 			}
 
-			let final_product = shifted_product as u32;
+			Self::deposit_event(Event::AccumulatorsReset(who, to));
 
-			// Write the new value to storage
-			ManualAccumulator::<T>::put(final_product);
+			Ok(())
+		}
 
-			// Emit event
-			Self::deposit_event(Event::ManualUpdated(new_factor, final_product));
+		/// Save the caller's three accumulators under `name`, replacing any snapshot that
+		/// already has that name.
+		#[pallet::call_index(7)]
+		#[pallet::weight(10_000)]
+		pub fn snapshot(origin: OriginFor<T>, name: SnapshotName<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			if !Snapshots::<T>::contains_key(&who, &name) {
+				SnapshotCount::<T>::try_mutate(&who, |count| -> DispatchResult {
+					ensure!(*count < T::MaxSnapshots::get(), Error::<T>::TooManySnapshots);
+					*count += 1;
+					Ok(())
+				})?;
+			}
+
+			let checkpoint = Checkpoint {
+				permill: Self::permil_value(&who),
+				fixed: Self::fixed_value(&who),
+				manual: Self::manual_value(&who),
+			};
+			Snapshots::<T>::insert(&who, &name, checkpoint);
+
+			Self::deposit_event(Event::SnapshotTaken(who, name, checkpoint));
 
 			Ok(())
 		}
+
+		/// Remove one of the caller's snapshots, making room for another.
+		#[pallet::call_index(8)]
+		#[pallet::weight(10_000)]
+		pub fn remove_snapshot(origin: OriginFor<T>, name: SnapshotName<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Snapshots::<T>::take(&who, &name).ok_or(Error::<T>::UnknownSnapshot)?;
+			SnapshotCount::<T>::mutate_exists(&who, |count| {
+				*count = count.map(|c| c.saturating_sub(1)).filter(|c| *c > 0)
+			});
+
+			Self::deposit_event(Event::SnapshotRemoved(who, name));
+
+			Ok(())
+		}
+	}
+}
+
+/// How far each accumulator implementation drifts from the exact product of a factor sequence.
+///
+/// All values are converted to `FixedU128`, which is precise enough to serve as the reference.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+pub struct DriftReport {
+	/// The product computed with `FixedU128`.
+	pub reference: FixedU128,
+	/// Distance between the `Permill` product and the reference.
+	pub permill_drift: FixedU128,
+	/// Distance between the `U16F16` product and the reference.
+	pub fixed_drift: FixedU128,
+	/// Distance between the manual Q16.16 product and the reference.
+	pub manual_drift: FixedU128,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Multiply one by each of `factors` in every representation, the way the `update_*` calls
+	/// do, and report how far the results are from each other.
+	///
+	/// The factors are given as `Permill` because it is the narrowest of the representations, so
+	/// every factor can be expressed in all three.
+	pub fn compare_drift(factors: Vec<Permill>) -> DriftReport {
+		let mut reference = FixedU128::one();
+		let mut permill = Permill::one();
+		let mut fixed = U16F16::from_num(1);
+		let mut manual = Q16::one();
+
+		for factor in factors {
+			let q16_factor = permill_to_q16(factor);
+			reference = reference.saturating_mul(factor.into());
+			permill = permill.saturating_mul(factor);
+			fixed = fixed.saturating_mul(U16F16::from_bits(q16_factor.to_bits()));
+			// Factors are at most one, so the product can only shrink and never overflows.
+			manual = manual.checked_mul(q16_factor, Rounding::Down).unwrap_or_default();
+		}
+
+		let q16_to_fixed_u128 =
+			|bits: u32| FixedU128::from_rational(bits.into(), u128::from(Q16::one().to_bits()));
+		let drift = |value: FixedU128| value.max(reference).saturating_sub(value.min(reference));
+
+		DriftReport {
+			reference,
+			permill_drift: drift(permill.into()),
+			fixed_drift: drift(q16_to_fixed_u128(fixed.to_bits())),
+			manual_drift: drift(q16_to_fixed_u128(manual.to_bits())),
+		}
 	}
 }
+
+/// The Q16.16 value closest to `p`.
+fn permill_to_q16(p: Permill) -> Q16 {
+	let parts = u64::from(p.deconstruct());
+	let accuracy = u64::from(Permill::ACCURACY);
+	let bits = ((parts << 16) + accuracy / 2) / accuracy;
+	// `p` is at most one, so this is at most `1 << 16`.
+	Q16::from_bits(bits as u32)
+}
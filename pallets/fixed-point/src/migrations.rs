@@ -0,0 +1,76 @@
+//! Storage migrations for the fixed-point pallet.
+
+use super::*;
+use frame_support::{
+	pallet_prelude::*,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{GetStorageVersion, OnRuntimeUpgrade},
+};
+use sp_std::marker::PhantomData;
+
+#[cfg(feature = "try-runtime")]
+use frame_support::sp_runtime::TryRuntimeError;
+
+const LOG_TARGET: &str = "runtime::fixed-point";
+
+pub mod v1 {
+	use super::*;
+
+	/// Removes the global accumulators left behind when they were keyed by account.
+	///
+	/// Each accumulator was a `StorageValue` stored under what is now the prefix of its map, so
+	/// the old value is exactly that key. The values aren't carried over: there is no account to
+	/// give them to, and every account starts from one.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> MigrateToV1<T> {
+		/// The keys of the old global accumulators.
+		fn old_keys() -> [Vec<u8>; 3] {
+			[
+				PermilAccumulator::<T>::final_prefix().to_vec(),
+				FixedAccumulator::<T>::final_prefix().to_vec(),
+				ManualAccumulator::<T>::final_prefix().to_vec(),
+			]
+		}
+	}
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"skipping migration to v1, storage is already at {:?}",
+					on_chain
+				);
+				return T::DbWeight::get().reads(1)
+			}
+
+			for key in Self::old_keys() {
+				unhashed::kill(&key);
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "removed the global accumulators");
+			T::DbWeight::get().reads_writes(1, 4)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			Ok(Vec::new())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"storage version should be 1 after the migration"
+			);
+			ensure!(
+				Self::old_keys().iter().all(|key| !unhashed::exists(key)),
+				"the global accumulators should be removed"
+			);
+			Ok(())
+		}
+	}
+}
@@ -1,5 +1,5 @@
 use crate as pallet_fixed_point;
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::traits::{ConstU16, ConstU32, ConstU64};
 use sp_core::H256;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
@@ -43,8 +43,13 @@ impl frame_system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+pub type MaxNameLength = ConstU32<8>;
+
 impl pallet_fixed_point::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type MaxNameLength = MaxNameLength;
+	type MaxSnapshots = ConstU32<2>;
+	type MaxAccumulators = ConstU32<2>;
 }
 
 // Build genesis storage according to the mock runtime.
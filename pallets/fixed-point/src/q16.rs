@@ -0,0 +1,242 @@
+//! An unsigned Q16.16 fixed-point number implemented by hand.
+//!
+//! [`Q16`] stores a number in a `u32` whose low 16 bits are the fractional part, so its raw bits
+//! are `value * 2^16`. This is the same layout as `substrate_fixed::types::U16F16`; the two are
+//! cross-checked in the tests below.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::RuntimeDebug;
+use scale_info::TypeInfo;
+pub use sp_arithmetic::Rounding;
+
+/// Number of fractional bits.
+const FRAC_BITS: u32 = 16;
+
+/// An unsigned fixed-point number with 16 integer and 16 fractional bits.
+#[derive(
+	Encode,
+	Decode,
+	MaxEncodedLen,
+	Clone,
+	Copy,
+	Default,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	TypeInfo,
+	RuntimeDebug,
+)]
+pub struct Q16(u32);
+
+impl Q16 {
+	/// The smallest representable step, `2^-16`.
+	pub const DELTA: Q16 = Q16(1);
+
+	/// Build a value from its raw bits, i.e. `bits / 2^16`.
+	pub const fn from_bits(bits: u32) -> Self {
+		Q16(bits)
+	}
+
+	/// The raw bits of the value.
+	pub const fn to_bits(self) -> u32 {
+		self.0
+	}
+
+	/// A whole number.
+	pub const fn from_integer(int: u16) -> Self {
+		Q16((int as u32) << FRAC_BITS)
+	}
+
+	pub const fn zero() -> Self {
+		Q16(0)
+	}
+
+	pub const fn one() -> Self {
+		Q16(1 << FRAC_BITS)
+	}
+
+	pub fn checked_add(self, other: Self) -> Option<Self> {
+		self.0.checked_add(other.0).map(Q16)
+	}
+
+	pub fn checked_sub(self, other: Self) -> Option<Self> {
+		self.0.checked_sub(other.0).map(Q16)
+	}
+
+	/// Multiply, rounding the bits that don't fit in the fractional part as requested.
+	///
+	/// Returns `None` if the result does not fit in 16 integer bits.
+	pub fn checked_mul(self, other: Self, rounding: Rounding) -> Option<Self> {
+		// The product of two Q16.16 numbers has 32 fractional bits, so it has to be shifted
+		// back down by 16. Doing it as a division lets us round rather than truncate.
+		let product = u64::from(self.0) * u64::from(other.0);
+		Self::from_wide(div_rounded(product, 1 << FRAC_BITS, rounding))
+	}
+
+	/// Divide, rounding the bits that don't fit in the fractional part as requested.
+	///
+	/// Returns `None` when dividing by zero or if the result does not fit in 16 integer bits.
+	pub fn checked_div(self, other: Self, rounding: Rounding) -> Option<Self> {
+		if other.0 == 0 {
+			return None
+		}
+		// Shifting the dividend up first keeps 16 fractional bits in the quotient.
+		let dividend = u64::from(self.0) << FRAC_BITS;
+		Self::from_wide(div_rounded(dividend, other.0.into(), rounding))
+	}
+
+	fn from_wide(bits: u64) -> Option<Self> {
+		u32::try_from(bits).ok().map(Q16)
+	}
+}
+
+/// `n / d`, rounded as requested. `d` must not be zero.
+fn div_rounded(n: u64, d: u64, rounding: Rounding) -> u64 {
+	let (quotient, remainder) = (n / d, n % d);
+	// `d` is at most `u32::MAX` so doubling the remainder can't overflow.
+	let round_up = match rounding {
+		Rounding::Down => false,
+		Rounding::Up => remainder > 0,
+		Rounding::NearestPrefDown => remainder * 2 > d,
+		Rounding::NearestPrefUp => remainder * 2 >= d,
+	};
+	quotient + u64::from(round_up)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+	use substrate_fixed::types::U16F16;
+
+	fn to_fixed(q: Q16) -> U16F16 {
+		U16F16::from_bits(q.to_bits())
+	}
+
+	/// How far `result` is from the exact quotient `n / d`, scaled by `d` so it stays an integer.
+	fn error_scaled(result: Q16, n: u64, d: u64) -> u64 {
+		let scaled = u64::from(result.to_bits()) * d;
+		scaled.abs_diff(n)
+	}
+
+	#[test]
+	fn constants_are_consistent() {
+		assert_eq!(Q16::one(), Q16::from_integer(1));
+		assert_eq!(Q16::one().to_bits(), 1 << 16);
+		assert_eq!(to_fixed(Q16::one()), U16F16::from_num(1));
+		assert_eq!(to_fixed(Q16::DELTA), U16F16::from_bits(1));
+	}
+
+	#[test]
+	fn basic_arithmetic() {
+		let two = Q16::from_integer(2);
+		let three = Q16::from_integer(3);
+		let half = Q16::from_bits(1 << 15);
+
+		assert_eq!(two.checked_add(three), Some(Q16::from_integer(5)));
+		assert_eq!(three.checked_sub(two), Some(Q16::one()));
+		assert_eq!(two.checked_sub(three), None);
+		assert_eq!(two.checked_mul(three, Rounding::Down), Some(Q16::from_integer(6)));
+		assert_eq!(three.checked_mul(half, Rounding::Down), Some(Q16::from_bits(3 << 15)));
+		assert_eq!(Q16::one().checked_div(two, Rounding::Down), Some(half));
+		assert_eq!(three.checked_div(Q16::zero(), Rounding::Down), None);
+	}
+
+	#[test]
+	fn overflow_is_detected() {
+		let big = Q16::from_integer(1 << 10);
+		assert_eq!(big.checked_mul(big, Rounding::Down), None);
+		assert_eq!(Q16::from_bits(u32::MAX).checked_add(Q16::DELTA), None);
+		assert_eq!(big.checked_div(Q16::DELTA, Rounding::Down), None);
+	}
+
+	#[test]
+	fn rounding_modes() {
+		// One third can't be represented exactly; its bits are 0x5555 with remainder 1/3.
+		let third = |rounding| Q16::one().checked_div(Q16::from_integer(3), rounding).unwrap();
+		assert_eq!(third(Rounding::Down).to_bits(), 0x5555);
+		assert_eq!(third(Rounding::Up).to_bits(), 0x5556);
+		assert_eq!(third(Rounding::NearestPrefDown).to_bits(), 0x5555);
+		assert_eq!(third(Rounding::NearestPrefUp).to_bits(), 0x5555);
+
+		// DELTA * 0.5 lies exactly halfway between zero and DELTA.
+		let half = Q16::from_bits(1 << 15);
+		let tie = |rounding| Q16::DELTA.checked_mul(half, rounding).unwrap();
+		assert_eq!(tie(Rounding::Down), Q16::zero());
+		assert_eq!(tie(Rounding::Up), Q16::DELTA);
+		assert_eq!(tie(Rounding::NearestPrefDown), Q16::zero());
+		assert_eq!(tie(Rounding::NearestPrefUp), Q16::DELTA);
+
+		// Rounding up can be what pushes a result out of range: this product is 2^32 - 2^-16.
+		let (a, b) = (Q16::from_bits((1 << 24) - 1), Q16::from_bits((1 << 24) + 1));
+		assert_eq!(a.checked_mul(b, Rounding::Down), Some(Q16::from_bits(u32::MAX)));
+		assert_eq!(a.checked_mul(b, Rounding::Up), None);
+	}
+
+	proptest! {
+		#[test]
+		fn add_matches_u16f16(a: u32, b: u32) {
+			let (a, b) = (Q16::from_bits(a), Q16::from_bits(b));
+			prop_assert_eq!(a.checked_add(b).map(to_fixed), to_fixed(a).checked_add(to_fixed(b)));
+		}
+
+		#[test]
+		fn sub_matches_u16f16(a: u32, b: u32) {
+			let (a, b) = (Q16::from_bits(a), Q16::from_bits(b));
+			prop_assert_eq!(a.checked_sub(b).map(to_fixed), to_fixed(a).checked_sub(to_fixed(b)));
+		}
+
+		#[test]
+		fn mul_rounding_down_matches_u16f16(a: u32, b: u32) {
+			let (a, b) = (Q16::from_bits(a), Q16::from_bits(b));
+			prop_assert_eq!(
+				a.checked_mul(b, Rounding::Down).map(to_fixed),
+				to_fixed(a).checked_mul(to_fixed(b))
+			);
+		}
+
+		#[test]
+		fn div_rounding_down_matches_u16f16(a: u32, b: u32) {
+			let (a, b) = (Q16::from_bits(a), Q16::from_bits(b));
+			prop_assert_eq!(
+				a.checked_div(b, Rounding::Down).map(to_fixed),
+				to_fixed(a).checked_div(to_fixed(b))
+			);
+		}
+
+		#[test]
+		fn mul_rounding_is_within_bounds(a: u32, b: u32) {
+			let exact = u64::from(a) * u64::from(b);
+			let (a, b) = (Q16::from_bits(a), Q16::from_bits(b));
+			let down = a.checked_mul(b, Rounding::Down);
+			if let Some(up) = a.checked_mul(b, Rounding::Up) {
+				// Rounding up is never more than one step above rounding down.
+				let down = down.unwrap().to_bits();
+				prop_assert!(up.to_bits() == down || up.to_bits() == down + 1);
+			}
+			for rounding in [Rounding::NearestPrefDown, Rounding::NearestPrefUp] {
+				if let Some(nearest) = a.checked_mul(b, rounding) {
+					// Within half a step of the exact product.
+					prop_assert!(error_scaled(nearest, exact, 1 << 16) * 2 <= 1 << 16);
+				}
+			}
+		}
+
+		#[test]
+		fn div_rounding_is_within_bounds(a: u32, b in 1u32..) {
+			let exact = u64::from(a) << 16;
+			let (a, b) = (Q16::from_bits(a), Q16::from_bits(b));
+			let down = a.checked_div(b, Rounding::Down);
+			if let Some(up) = a.checked_div(b, Rounding::Up) {
+				let down = down.unwrap().to_bits();
+				prop_assert!(up.to_bits() == down || up.to_bits() == down + 1);
+			}
+			for rounding in [Rounding::NearestPrefDown, Rounding::NearestPrefUp] {
+				if let Some(nearest) = a.checked_div(b, rounding) {
+					prop_assert!(error_scaled(nearest, exact, b.to_bits().into()) * 2 <= u64::from(b.to_bits()));
+				}
+			}
+		}
+	}
+}
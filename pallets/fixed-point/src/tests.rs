@@ -1,14 +1,22 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
-use sp_arithmetic::Permill;
+use crate::{
+	migrations, mock::*, Accumulator, Checkpoint, Error, Event, FixedAccumulator, FixedValue,
+	ManualAccumulator, Operation, OverflowMode, PermilAccumulator, Q16,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	BoundedVec,
+};
+use sp_arithmetic::{traits::Zero, FixedU128, Perbill, Permill};
 use substrate_fixed::types::U16F16;
 
 #[test]
 fn all_accumulators_start_at_one() {
 	new_test_ext().execute_with(|| {
-		assert_eq!(FixedPoint::manual_value(), 1 << 16);
-		assert_eq!(FixedPoint::permil_value(), Permill::one());
-		assert_eq!(FixedPoint::fixed_value(), 1);
+		assert_eq!(FixedPoint::manual_value(1u64), 1 << 16);
+		assert_eq!(FixedPoint::permil_value(1u64), Permill::one());
+		assert_eq!(FixedPoint::fixed_value(1u64), 1);
 	})
 }
 
@@ -24,13 +32,13 @@ fn fixed_impl_works() {
 		assert_ok!(FixedPoint::update_fixed(RuntimeOrigin::signed(1), half));
 
 		// Ensure the new value is correct
-		assert_eq!(FixedPoint::fixed_value(), half);
+		assert_eq!(FixedPoint::fixed_value(1u64), half);
 
 		// Multiply by half again
 		assert_ok!(FixedPoint::update_fixed(RuntimeOrigin::signed(1), half));
 
 		// Ensure the new value is correct
-		assert_eq!(FixedPoint::fixed_value(), quarter);
+		assert_eq!(FixedPoint::fixed_value(1u64), quarter);
 
 		// Test that the expected events were emitted
 		let our_events = System::events()
@@ -43,7 +51,7 @@ fn fixed_impl_works() {
 			.collect::<Vec<_>>();
 
 		let expected_events =
-			vec![Event::FixedUpdated(half, half), Event::FixedUpdated(half, quarter)];
+			vec![Event::FixedUpdated(1, half, half), Event::FixedUpdated(1, half, quarter)];
 
 		assert_eq!(our_events, expected_events);
 	})
@@ -65,3 +73,328 @@ fn fixed_impl_overflows() {
 		);
 	})
 }
+
+#[test]
+fn manual_impl_works() {
+	new_test_ext().execute_with(|| {
+		let half = 1 << 15;
+		let quarter = 1 << 14;
+
+		// Multiply by half
+		assert_ok!(FixedPoint::update_manual(RuntimeOrigin::signed(1), half));
+		assert_eq!(FixedPoint::manual_value(1u64), half);
+
+		// Multiply by half again
+		assert_ok!(FixedPoint::update_manual(RuntimeOrigin::signed(1), half));
+		assert_eq!(FixedPoint::manual_value(1u64), quarter);
+
+		// The result agrees with the substrate-fixed implementation
+		assert_ok!(FixedPoint::update_fixed(RuntimeOrigin::signed(1), U16F16::from_bits(half)));
+		assert_ok!(FixedPoint::update_fixed(RuntimeOrigin::signed(1), U16F16::from_bits(half)));
+		assert_eq!(FixedPoint::fixed_value(1u64).to_bits(), FixedPoint::manual_value(1u64));
+
+		System::assert_last_event(
+			Event::FixedUpdated(1, U16F16::from_bits(half), U16F16::from_bits(quarter)).into(),
+		);
+		System::assert_has_event(Event::ManualUpdated(1, half, quarter).into());
+	})
+}
+
+#[test]
+fn manual_impl_overflows() {
+	new_test_ext().execute_with(|| {
+		// Multiply by 2 ^ 10
+		assert_ok!(FixedPoint::update_manual(RuntimeOrigin::signed(1), (1 << 10) << 16));
+
+		// Multiple by an additional 2 ^ 7 which should cause the overflow
+		assert_noop!(
+			FixedPoint::update_manual(RuntimeOrigin::signed(1), (1 << 7) << 16),
+			Error::<Test>::Overflow
+		);
+	})
+}
+
+#[test]
+fn create_accumulator_works() {
+	new_test_ext().execute_with(|| {
+		let initial = FixedValue::Perbill(Perbill::from_percent(50));
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), initial));
+		assert_ok!(FixedPoint::create_accumulator(
+			RuntimeOrigin::signed(2),
+			FixedValue::U16F16(U16F16::from_num(2))
+		));
+
+		assert_eq!(FixedPoint::accumulator_count(), 2);
+		assert_eq!(FixedPoint::accumulators(0), Some(Accumulator { owner: 1, value: initial }));
+		System::assert_has_event(Event::AccumulatorCreated(0, 1, initial).into());
+	})
+}
+
+#[test]
+fn update_accumulator_works() {
+	new_test_ext().execute_with(|| {
+		let two = FixedValue::U16F16(U16F16::from_num(2));
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), two));
+
+		assert_ok!(FixedPoint::update_accumulator(
+			RuntimeOrigin::signed(1),
+			0,
+			Operation::Pow(3),
+			OverflowMode::Checked
+		));
+		let eight = FixedValue::U16F16(U16F16::from_num(8));
+		assert_eq!(FixedPoint::accumulators(0).unwrap().value, eight);
+		System::assert_last_event(Event::AccumulatorUpdated(0, Operation::Pow(3), eight).into());
+
+		assert_ok!(FixedPoint::update_accumulator(
+			RuntimeOrigin::signed(1),
+			0,
+			Operation::Div(two),
+			OverflowMode::Checked
+		));
+		assert_eq!(
+			FixedPoint::accumulators(0).unwrap().value,
+			FixedValue::U16F16(U16F16::from_num(4))
+		);
+	})
+}
+
+#[test]
+fn update_accumulator_overflow_modes() {
+	new_test_ext().execute_with(|| {
+		let big = FixedValue::U16F16(U16F16::from_num(1 << 10));
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), big));
+
+		assert_noop!(
+			FixedPoint::update_accumulator(
+				RuntimeOrigin::signed(1),
+				0,
+				Operation::Mul(big),
+				OverflowMode::Checked
+			),
+			Error::<Test>::Overflow
+		);
+
+		assert_ok!(FixedPoint::update_accumulator(
+			RuntimeOrigin::signed(1),
+			0,
+			Operation::Mul(big),
+			OverflowMode::Saturating
+		));
+		assert_eq!(
+			FixedPoint::accumulators(0).unwrap().value,
+			FixedValue::U16F16(U16F16::max_value())
+		);
+	})
+}
+
+#[test]
+fn update_accumulator_handles_errors() {
+	new_test_ext().execute_with(|| {
+		let zero = FixedValue::Permill(Permill::zero());
+		assert_noop!(
+			FixedPoint::update_accumulator(
+				RuntimeOrigin::signed(1),
+				0,
+				Operation::Pow(2),
+				OverflowMode::Checked
+			),
+			Error::<Test>::UnknownAccumulator
+		);
+
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), zero));
+		assert_noop!(
+			FixedPoint::update_accumulator(
+				RuntimeOrigin::signed(2),
+				0,
+				Operation::Pow(2),
+				OverflowMode::Checked
+			),
+			Error::<Test>::NotOwner
+		);
+		assert_noop!(
+			FixedPoint::update_accumulator(
+				RuntimeOrigin::signed(1),
+				0,
+				Operation::Div(zero),
+				OverflowMode::Saturating
+			),
+			Error::<Test>::DivisionByZero
+		);
+		assert_noop!(
+			FixedPoint::update_accumulator(
+				RuntimeOrigin::signed(1),
+				0,
+				Operation::Add(FixedValue::Perbill(Perbill::zero())),
+				OverflowMode::Checked
+			),
+			Error::<Test>::RepresentationMismatch
+		);
+	})
+}
+
+#[test]
+fn remove_accumulator_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(FixedPoint::create_accumulator(
+			RuntimeOrigin::signed(1),
+			FixedValue::Permill(Permill::one())
+		));
+
+		assert_noop!(
+			FixedPoint::remove_accumulator(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NotOwner
+		);
+		assert_ok!(FixedPoint::remove_accumulator(RuntimeOrigin::signed(1), 0));
+		assert_eq!(FixedPoint::accumulators(0), None);
+		System::assert_last_event(Event::AccumulatorRemoved(0).into());
+		assert_noop!(
+			FixedPoint::remove_accumulator(RuntimeOrigin::signed(1), 0),
+			Error::<Test>::UnknownAccumulator
+		);
+	})
+}
+
+#[test]
+fn accumulators_are_limited_per_account() {
+	new_test_ext().execute_with(|| {
+		let one = FixedValue::Permill(Permill::one());
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), one));
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), one));
+		assert_noop!(
+			FixedPoint::create_accumulator(RuntimeOrigin::signed(1), one),
+			Error::<Test>::TooManyAccumulators
+		);
+		assert_eq!(FixedPoint::owned_accumulators(1), 2);
+
+		// Other accounts have their own limit
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(2), one));
+
+		// Removing one makes room for another
+		assert_ok!(FixedPoint::remove_accumulator(RuntimeOrigin::signed(1), 0));
+		assert_eq!(FixedPoint::owned_accumulators(1), 1);
+		assert_ok!(FixedPoint::create_accumulator(RuntimeOrigin::signed(1), one));
+	})
+}
+
+#[test]
+fn accumulators_are_per_account() {
+	new_test_ext().execute_with(|| {
+		let half = U16F16::from_num(1) / 2;
+		assert_ok!(FixedPoint::update_fixed(RuntimeOrigin::signed(1), half));
+		assert_ok!(FixedPoint::update_permil(RuntimeOrigin::signed(1), Permill::zero()));
+		assert_ok!(FixedPoint::update_manual(RuntimeOrigin::signed(1), 0));
+
+		// Another account's accumulators are untouched
+		assert_eq!(FixedPoint::fixed_value(1u64), half);
+		assert_eq!(FixedPoint::fixed_value(2u64), 1);
+		assert_eq!(FixedPoint::permil_value(2u64), Permill::one());
+		assert_eq!(FixedPoint::manual_value(2u64), 1 << 16);
+		System::assert_last_event(Event::ManualUpdated(1, 0, 0).into());
+	})
+}
+
+fn name(s: &[u8]) -> BoundedVec<u8, MaxNameLength> {
+	s.to_vec().try_into().unwrap()
+}
+
+#[test]
+fn snapshot_and_reset_work() {
+	new_test_ext().execute_with(|| {
+		let half = U16F16::from_num(1) / 2;
+		assert_ok!(FixedPoint::update_fixed(RuntimeOrigin::signed(1), half));
+		assert_ok!(FixedPoint::snapshot(RuntimeOrigin::signed(1), name(b"half")));
+
+		let checkpoint = Checkpoint { permill: Permill::one(), fixed: half, manual: 1 << 16 };
+		assert_eq!(FixedPoint::snapshots(1u64, name(b"half")), Some(checkpoint));
+		assert_eq!(FixedPoint::snapshot_count(1u64), 1);
+		System::assert_last_event(Event::SnapshotTaken(1, name(b"half"), checkpoint).into());
+
+		// Reset to one
+		assert_ok!(FixedPoint::reset(RuntimeOrigin::signed(1), None));
+		assert_eq!(FixedPoint::fixed_value(1u64), 1);
+		System::assert_last_event(Event::AccumulatorsReset(1, None).into());
+
+		// Reset to the snapshot
+		assert_ok!(FixedPoint::reset(RuntimeOrigin::signed(1), Some(name(b"half"))));
+		assert_eq!(FixedPoint::fixed_value(1u64), half);
+
+		// Snapshots are per account too
+		assert_noop!(
+			FixedPoint::reset(RuntimeOrigin::signed(2), Some(name(b"half"))),
+			Error::<Test>::UnknownSnapshot
+		);
+	})
+}
+
+#[test]
+fn snapshots_are_bounded() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(FixedPoint::snapshot(RuntimeOrigin::signed(1), name(b"a")));
+		assert_ok!(FixedPoint::snapshot(RuntimeOrigin::signed(1), name(b"b")));
+		assert_noop!(
+			FixedPoint::snapshot(RuntimeOrigin::signed(1), name(b"c")),
+			Error::<Test>::TooManySnapshots
+		);
+
+		// Overwriting an existing snapshot doesn't need a new slot
+		assert_ok!(FixedPoint::update_permil(RuntimeOrigin::signed(1), Permill::zero()));
+		assert_ok!(FixedPoint::snapshot(RuntimeOrigin::signed(1), name(b"b")));
+		assert_eq!(FixedPoint::snapshots(1u64, name(b"b")).unwrap().permill, Permill::zero());
+
+		// Removing one makes room for another
+		assert_ok!(FixedPoint::remove_snapshot(RuntimeOrigin::signed(1), name(b"a")));
+		assert_noop!(
+			FixedPoint::remove_snapshot(RuntimeOrigin::signed(1), name(b"a")),
+			Error::<Test>::UnknownSnapshot
+		);
+		assert_eq!(FixedPoint::snapshot_count(1u64), 1);
+		assert_ok!(FixedPoint::snapshot(RuntimeOrigin::signed(1), name(b"c")));
+		assert_eq!(FixedPoint::snapshot_count(1u64), 2);
+	})
+}
+
+#[test]
+fn compare_drift_works() {
+	new_test_ext().execute_with(|| {
+		// Halving is exact in every representation
+		let report = FixedPoint::compare_drift(vec![Permill::from_percent(50); 4]);
+		assert_eq!(report.reference, FixedU128::from_rational(1, 16));
+		assert_eq!(report.permill_drift, FixedU128::zero());
+		assert_eq!(report.fixed_drift, FixedU128::zero());
+		assert_eq!(report.manual_drift, FixedU128::zero());
+
+		// A tenth isn't, and the binary representations fall behind the decimal one
+		let report = FixedPoint::compare_drift(vec![Permill::from_percent(10); 3]);
+		assert_eq!(report.reference, FixedU128::from_rational(1, 1000));
+		assert_eq!(report.permill_drift, FixedU128::zero());
+		assert!(report.fixed_drift > FixedU128::zero());
+		// The manual implementation truncates exactly like substrate-fixed
+		assert_eq!(report.manual_drift, report.fixed_drift);
+		assert!(
+			report.manual_drift < FixedU128::from_rational(1, u128::from(Q16::one().to_bits()))
+		);
+	})
+}
+
+#[test]
+fn migration_to_v1_removes_global_accumulators() {
+	new_test_ext().execute_with(|| {
+		// The accumulators as they were stored before being keyed by account
+		unhashed::put(&PermilAccumulator::<Test>::final_prefix(), &Permill::from_percent(50));
+		unhashed::put(&FixedAccumulator::<Test>::final_prefix(), &U16F16::from_num(2));
+		unhashed::put(&ManualAccumulator::<Test>::final_prefix(), &(3u32 << 16));
+		StorageVersion::new(0).put::<FixedPoint>();
+		ManualAccumulator::<Test>::insert(1u64, 5);
+
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(FixedPoint::on_chain_storage_version(), 1);
+		assert!(!unhashed::exists(&PermilAccumulator::<Test>::final_prefix()));
+		assert!(!unhashed::exists(&FixedAccumulator::<Test>::final_prefix()));
+		assert!(!unhashed::exists(&ManualAccumulator::<Test>::final_prefix()));
+		// Accounts' accumulators are untouched
+		assert_eq!(FixedPoint::manual_value(1u64), 5);
+		assert_eq!(FixedPoint::permil_value(1u64), Permill::one());
+	})
+}
@@ -0,0 +1,75 @@
+//! RPC interface for the last-caller pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+pub use last_caller_runtime_api::LastCallerApi as LastCallerRuntimeApi;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+
+#[rpc(client, server)]
+pub trait LastCallerApi<BlockHash, AccountId, BlockNumber> {
+	/// Every instance of the pallet by name, with its last caller and the block they called in.
+	#[method(name = "lastCaller_lastCalls")]
+	fn last_calls(
+		&self,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(String, Option<(AccountId, BlockNumber)>)>>;
+}
+
+/// Provides RPC methods to query the last-caller pallet's instances.
+pub struct LastCaller<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> LastCaller<C, Block> {
+	/// Create new `LastCaller` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block, AccountId, BlockNumber>
+	LastCallerApiServer<<Block as BlockT>::Hash, AccountId, BlockNumber> for LastCaller<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: LastCallerRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec + Send + Sync + 'static,
+	BlockNumber: Codec + Send + Sync + 'static,
+{
+	fn last_calls(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(String, Option<(AccountId, BlockNumber)>)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		let calls = api.last_calls(at).map_err(runtime_error_into_rpc_err)?;
+		// Instance names come from `construct_runtime!`, so they are always valid UTF-8.
+		Ok(calls
+			.into_iter()
+			.map(|(name, call)| (String::from_utf8_lossy(&name).into_owned(), call))
+			.collect())
+	}
+}
+
+/// Error code for runtime errors.
+const RUNTIME_ERROR: i32 = 1;
+
+/// Converts a runtime trap into an RPC error.
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		RUNTIME_ERROR,
+		"Runtime error",
+		Some(format!("{:?}", err)),
+	))
+	.into()
+}
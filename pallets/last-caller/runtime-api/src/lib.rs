@@ -0,0 +1,14 @@
+//! Runtime API definition for the last-caller pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait LastCallerApi<AccountId, BlockNumber> where AccountId: Codec, BlockNumber: Codec {
+		/// The name of every instance of the pallet in the runtime, with its last caller and the
+		/// block they called in.
+		fn last_calls() -> Vec<(Vec<u8>, Option<(AccountId, BlockNumber)>)>;
+	}
+}
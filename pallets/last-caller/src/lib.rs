@@ -1,23 +1,57 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+//! An instantiable pallet recording who called it.
+//!
+//! Every instance keeps the most recent `MaxHistory` calls, oldest first, together with the block
+//! they were made in, and counts how many times each account has called it. A runtime can include
+//! the pallet several times with `Instance1`, `Instance2`, ... or once with the default instance.
+
+use frame_support::traits::PalletInfoAccess;
+use frame_system::pallet_prelude::BlockNumberFor;
 pub use pallet::*;
+use sp_std::vec::Vec;
+
+pub mod migrations;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
 
 #[frame_support::pallet(dev_mode)]
 pub mod pallet {
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 
+	/// The current storage version.
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T, I = ()>(_);
 
 	#[pallet::config]
 	pub trait Config<I: 'static = ()>: frame_system::Config {
 		type RuntimeEvent: From<Event<Self, I>>
 			+ IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// How many calls each instance remembers. Older calls are dropped first.
+		#[pallet::constant]
+		type MaxHistory: Get<u32>;
 	}
 
+	/// The most recent callers and the block they called in, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn history)]
+	pub type History<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, BoundedVec<(T::AccountId, BlockNumberFor<T>), T::MaxHistory>, ValueQuery>;
+
+	/// How many times each account has called this instance.
 	#[pallet::storage]
-	pub type Caller<T: Config<I>, I: 'static = ()> = StorageValue<_, T::AccountId, OptionQuery>;
+	#[pallet::getter(fn call_count)]
+	pub type CallCount<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
@@ -31,12 +65,47 @@ pub mod pallet {
 		#[pallet::weight(10_000)]
 		pub fn call_pal(origin: OriginFor<T>) -> DispatchResult {
 			let caller = ensure_signed(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
 
 			// When writing to storage, we supply, not only a configuration T, but also an
 			// instance, I.
-			<Caller<T, I>>::put(&caller);
+			<History<T, I>>::mutate(|history| {
+				if history.len() as u32 >= T::MaxHistory::get() && !history.is_empty() {
+					history.remove(0);
+				}
+				// This only fails if `MaxHistory` is zero, in which case nothing is kept.
+				let _ = history.try_push((caller.clone(), now));
+			});
+			<CallCount<T, I>>::mutate(&caller, |count| *count = count.saturating_add(1));
+
 			Self::deposit_event(Event::Called(caller));
 			Ok(().into())
 		}
 	}
 }
+
+impl<T: Config<I>, I: 'static> Pallet<T, I> {
+	/// The last caller of this instance and the block they called in, if any.
+	pub fn last_call() -> Option<(T::AccountId, BlockNumberFor<T>)> {
+		Self::history().last().cloned()
+	}
+}
+
+/// The last call of one instance, tagged with the instance's name in the runtime.
+pub type InstanceLastCall<AccountId, BlockNumber> = (Vec<u8>, Option<(AccountId, BlockNumber)>);
+
+/// Lists the last call of every instance in a tuple of instances.
+///
+/// A runtime implements its runtime API with a tuple of all the instances it includes, e.g.
+/// `(LastCallerInstance1, LastCallerInstance2)`, so clients don't need to know which instances
+/// exist or how their storage is prefixed.
+#[impl_trait_for_tuples::impl_for_tuples(8)]
+pub trait LastCalls<AccountId, BlockNumber> {
+	fn last_calls(calls: &mut Vec<InstanceLastCall<AccountId, BlockNumber>>);
+}
+
+impl<T: Config<I>, I: 'static> LastCalls<T::AccountId, BlockNumberFor<T>> for Pallet<T, I> {
+	fn last_calls(calls: &mut Vec<InstanceLastCall<T::AccountId, BlockNumberFor<T>>>) {
+		calls.push((<Self as PalletInfoAccess>::name().as_bytes().to_vec(), Self::last_call()));
+	}
+}
@@ -0,0 +1,78 @@
+//! Storage migrations for the last-caller pallet.
+
+use super::*;
+use frame_support::{
+	pallet_prelude::*,
+	storage::{storage_prefix, unhashed},
+	traits::{GetStorageVersion, OnRuntimeUpgrade},
+};
+use sp_std::marker::PhantomData;
+
+#[cfg(feature = "try-runtime")]
+use frame_support::sp_runtime::TryRuntimeError;
+
+const LOG_TARGET: &str = "runtime::last-caller";
+
+pub mod v1 {
+	use super::*;
+
+	/// Moves the single `Caller` that an instance used to keep into its `History`.
+	///
+	/// The block of that call was never stored, so it is recorded at the block of the upgrade.
+	/// The caller is also counted as having called at least once. Run it once for each instance.
+	pub struct MigrateToV1<T, I = ()>(PhantomData<(T, I)>);
+
+	impl<T: Config<I>, I: 'static> MigrateToV1<T, I> {
+		/// The key of the old `Caller` value of this instance.
+		fn caller_key() -> [u8; 32] {
+			storage_prefix(<Pallet<T, I> as PalletInfoAccess>::name().as_bytes(), b"Caller")
+		}
+	}
+
+	impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for MigrateToV1<T, I> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T, I>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"skipping migration to v1, storage is already at {:?}",
+					on_chain
+				);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut writes = 1;
+			if let Some(caller) = unhashed::take::<T::AccountId>(&Self::caller_key()) {
+				let now = <frame_system::Pallet<T>>::block_number();
+				<History<T, I>>::mutate(|history| {
+					// Only fails if `MaxHistory` is zero, in which case nothing is kept.
+					let _ = history.try_push((caller.clone(), now));
+				});
+				<CallCount<T, I>>::mutate(&caller, |count| *count = (*count).max(1));
+				writes += 3;
+			}
+			StorageVersion::new(1).put::<Pallet<T, I>>();
+
+			log::info!(target: LOG_TARGET, "migrated the last caller to v1");
+			T::DbWeight::get().reads_writes(4, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			Ok(unhashed::get_raw(&Self::caller_key()).unwrap_or_default())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(
+				Pallet::<T, I>::on_chain_storage_version() == 1,
+				"storage version should be 1 after the migration"
+			);
+			ensure!(!unhashed::exists(&Self::caller_key()), "`Caller` should be removed");
+			if let Ok(caller) = T::AccountId::decode(&mut &state[..]) {
+				ensure!(<CallCount<T, I>>::get(&caller) > 0, "the old caller should be counted");
+			}
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,70 @@
+use crate as last_caller;
+use frame_support::traits::{ConstU16, ConstU32, ConstU64};
+use sp_core::H256;
+use sp_runtime::{
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet, with two named instances and the default one.
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		LastCallerInstance1: last_caller::<Instance1>,
+		LastCallerInstance2: last_caller::<Instance2>,
+		DefaultInstance: last_caller,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Nonce = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Block = Block;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl last_caller::Config<last_caller::Instance1> for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type MaxHistory = ConstU32<2>;
+}
+
+impl last_caller::Config<last_caller::Instance2> for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type MaxHistory = ConstU32<4>;
+}
+
+// The default instance only counts calls.
+impl last_caller::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type MaxHistory = ConstU32<0>;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,106 @@
+use crate::{migrations, mock::*, Event, LastCalls};
+use frame_support::{
+	assert_ok,
+	storage::{storage_prefix, unhashed},
+	traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+};
+
+#[test]
+fn history_keeps_the_most_recent_calls() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(LastCallerInstance1::last_call(), None);
+
+		assert_ok!(LastCallerInstance1::call_pal(RuntimeOrigin::signed(1)));
+		System::set_block_number(2);
+		assert_ok!(LastCallerInstance1::call_pal(RuntimeOrigin::signed(2)));
+		assert_eq!(LastCallerInstance1::history().into_inner(), vec![(1, 1), (2, 2)]);
+
+		// The oldest call is dropped once the history is full
+		System::set_block_number(3);
+		assert_ok!(LastCallerInstance1::call_pal(RuntimeOrigin::signed(1)));
+		assert_eq!(LastCallerInstance1::history().into_inner(), vec![(2, 2), (1, 3)]);
+		assert_eq!(LastCallerInstance1::last_call(), Some((1, 3)));
+
+		System::assert_last_event(Event::<Test, crate::Instance1>::Called(1).into());
+	})
+}
+
+#[test]
+fn calls_are_counted_per_account() {
+	new_test_ext().execute_with(|| {
+		for _ in 0..3 {
+			assert_ok!(LastCallerInstance1::call_pal(RuntimeOrigin::signed(1)));
+		}
+		assert_ok!(LastCallerInstance1::call_pal(RuntimeOrigin::signed(2)));
+
+		assert_eq!(LastCallerInstance1::call_count(1u64), 3);
+		assert_eq!(LastCallerInstance1::call_count(2u64), 1);
+		assert_eq!(LastCallerInstance1::call_count(3u64), 0);
+	})
+}
+
+#[test]
+fn instances_are_independent() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(LastCallerInstance1::call_pal(RuntimeOrigin::signed(1)));
+		assert_ok!(LastCallerInstance2::call_pal(RuntimeOrigin::signed(2)));
+		assert_ok!(DefaultInstance::call_pal(RuntimeOrigin::signed(3)));
+
+		assert_eq!(LastCallerInstance1::last_call(), Some((1, 1)));
+		assert_eq!(LastCallerInstance2::last_call(), Some((2, 1)));
+		assert_eq!(LastCallerInstance2::call_count(1u64), 0);
+
+		// Without any history the default instance still counts calls
+		assert_eq!(DefaultInstance::last_call(), None);
+		assert_eq!(DefaultInstance::call_count(3u64), 1);
+		System::assert_last_event(Event::<Test>::Called(3).into());
+	})
+}
+
+#[test]
+fn last_calls_lists_every_instance() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(LastCallerInstance2::call_pal(RuntimeOrigin::signed(2)));
+		assert_ok!(DefaultInstance::call_pal(RuntimeOrigin::signed(3)));
+
+		let mut calls = Vec::new();
+		<(LastCallerInstance1, LastCallerInstance2, DefaultInstance) as LastCalls<_, _>>::last_calls(
+			&mut calls,
+		);
+		assert_eq!(
+			calls,
+			vec![
+				(b"LastCallerInstance1".to_vec(), None),
+				(b"LastCallerInstance2".to_vec(), Some((2, 1))),
+				(b"DefaultInstance".to_vec(), None),
+			]
+		);
+	})
+}
+
+#[test]
+fn migration_to_v1_keeps_the_last_caller() {
+	new_test_ext().execute_with(|| {
+		// Each instance kept only its last caller before
+		for (instance, caller) in
+			[(&b"LastCallerInstance1"[..], 7u64), (&b"DefaultInstance"[..], 8)]
+		{
+			unhashed::put(&storage_prefix(instance, b"Caller"), &caller);
+		}
+		StorageVersion::new(0).put::<LastCallerInstance1>();
+		StorageVersion::new(0).put::<DefaultInstance>();
+		System::set_block_number(5);
+
+		migrations::v1::MigrateToV1::<Test, crate::Instance1>::on_runtime_upgrade();
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(LastCallerInstance1::on_chain_storage_version(), 1);
+		assert_eq!(LastCallerInstance1::last_call(), Some((7, 5)));
+		assert_eq!(LastCallerInstance1::call_count(7), 1);
+		assert!(!unhashed::exists(&storage_prefix(b"LastCallerInstance1", b"Caller")));
+		// The default instance keeps no history, but still counts the call
+		assert_eq!(DefaultInstance::last_call(), None);
+		assert_eq!(DefaultInstance::call_count(8), 1);
+		assert!(!unhashed::exists(&storage_prefix(b"DefaultInstance", b"Caller")));
+	})
+}
@@ -0,0 +1,101 @@
+//! Benchmarking setup for map-set
+#![cfg(feature = "runtime-benchmarks")]
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as MapSet;
+use frame_benchmarking::v2::*;
+use frame_support::{
+	traits::{ChangeMembers, EnsureOrigin, Get},
+	BoundedVec,
+};
+use sp_std::vec::Vec;
+
+const SEED: u32 = 0;
+
+/// Fill the set with `m` members, bypassing the origin checks.
+fn set_members<T: Config>(m: u32) -> Vec<T::AccountId> {
+	let mut members: Vec<T::AccountId> = (0..m).map(|i| account("member", i, SEED)).collect();
+	members.sort();
+	for member in &members {
+		Members::<T>::insert(member, ());
+	}
+	MemberCount::<T>::put(m);
+	T::MembershipChanged::set_members_sorted(&members, &[]);
+	members
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	/// Add a member to a set that already holds `m - 1` members.
+	#[benchmark]
+	fn add_member(m: Linear<1, { T::MaxMembers::get() }>) -> Result<(), BenchmarkError> {
+		set_members::<T>(m - 1);
+		let new_member: T::AccountId = account("new", 0, SEED);
+		let origin =
+			T::AddOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, new_member.clone());
+
+		assert!(Members::<T>::contains_key(&new_member));
+		assert_eq!(MemberCount::<T>::get(), m);
+		Ok(())
+	}
+
+	/// Remove a member from a set of `m` members.
+	#[benchmark]
+	fn remove_member(m: Linear<1, { T::MaxMembers::get() }>) -> Result<(), BenchmarkError> {
+		let members = set_members::<T>(m);
+		let to_remove = members.last().unwrap().clone();
+		let origin =
+			T::RemoveOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, to_remove.clone());
+
+		assert!(!Members::<T>::contains_key(&to_remove));
+		assert_eq!(MemberCount::<T>::get(), m - 1);
+		Ok(())
+	}
+
+	/// Swap one of `m` members for a new account.
+	#[benchmark]
+	fn swap_member(m: Linear<1, { T::MaxMembers::get() }>) -> Result<(), BenchmarkError> {
+		let members = set_members::<T>(m);
+		let to_remove = members.last().unwrap().clone();
+		let to_add: T::AccountId = account("new", 0, SEED);
+		let origin =
+			T::SwapOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, to_remove.clone(), to_add.clone());
+
+		assert!(!Members::<T>::contains_key(&to_remove));
+		assert!(Members::<T>::contains_key(&to_add));
+		Ok(())
+	}
+
+	/// Replace a set of `n` members with `m` new members.
+	#[benchmark]
+	fn reset_members(
+		m: Linear<1, { T::MaxMembers::get() }>,
+		n: Linear<0, { T::MaxMembers::get() }>,
+	) -> Result<(), BenchmarkError> {
+		set_members::<T>(n);
+		let new_members: BoundedVec<T::AccountId, T::MaxMembers> =
+			BoundedVec::truncate_from((0..m).map(|i| account("new", i, SEED)).collect());
+		let origin =
+			T::ResetOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, new_members);
+
+		assert_eq!(MemberCount::<T>::get(), m);
+		Ok(())
+	}
+
+	impl_benchmark_test_suite!(MapSet, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -2,7 +2,7 @@
 
 use account_set::AccountSet;
 pub use pallet::*;
-use sp_std::collections::btree_set::BTreeSet;
+use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
 
 #[cfg(test)]
 mod mock;
@@ -10,20 +10,46 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod weights;
+pub use weights::*;
+
 #[frame_support::pallet(dev_mode)]
 pub mod pallet {
-	use frame_support::pallet_prelude::*;
+	use frame_support::{pallet_prelude::*, traits::ChangeMembers};
 	use frame_system::pallet_prelude::*;
+	use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
 
 	#[pallet::pallet]
 	pub struct Pallet<T>(_);
 
-	/// A maximum number of members. When membership reaches this number, no new members may join.
-	pub const MAX_MEMBERS: u32 = 16;
-
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Required origin for adding a member.
+		type AddOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Required origin for removing a member.
+		type RemoveOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Required origin for swapping a member for another account.
+		type SwapOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Required origin for replacing all the members at once.
+		type ResetOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// The maximum number of members. When membership reaches this number, no new members
+		/// may join.
+		#[pallet::constant]
+		type MaxMembers: Get<u32>;
+
+		/// Notified with the incoming and outgoing members whenever the set changes.
+		type MembershipChanged: ChangeMembers<Self::AccountId>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
 	#[pallet::storage]
@@ -38,6 +64,10 @@ pub mod pallet {
 	pub enum Event<T: Config> {
 		MemberAdded(T::AccountId),
 		MemberRemoved(T::AccountId),
+		/// A member has been replaced. [removed, added]
+		MembersSwapped(T::AccountId, T::AccountId),
+		/// All members have been replaced.
+		MembersReset,
 	}
 
 	#[pallet::error]
@@ -49,41 +79,144 @@ pub mod pallet {
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
+		/// Add `who` to the set.
+		///
+		/// May only be called from `T::AddOrigin`.
 		#[pallet::call_index(0)]
-		#[pallet::weight(10_000)]
-		pub fn add_member(origin: OriginFor<T>) -> DispatchResult {
-			let new_member = ensure_signed(origin)?;
+		#[pallet::weight(T::WeightInfo::add_member(T::MaxMembers::get()))]
+		pub fn add_member(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::AddOrigin::ensure_origin(origin)?;
 			let member_count = <MemberCount<T>>::get();
 
-			ensure!(member_count < MAX_MEMBERS, Error::<T>::MembershipLimitReached);
+			ensure!(member_count < T::MaxMembers::get(), Error::<T>::MembershipLimitReached);
 
-			ensure!(!<Members<T>>::contains_key(&new_member), Error::<T>::AlreadyMember);
+			ensure!(!<Members<T>>::contains_key(&who), Error::<T>::AlreadyMember);
 
-			<Members<T>>::insert(&new_member, ());
+			<Members<T>>::insert(&who, ());
 			<MemberCount<T>>::mutate(|m| *m += 1);
-			Self::deposit_event(Event::MemberAdded(new_member));
+
+			T::MembershipChanged::change_members_sorted(
+				&[who.clone()],
+				&[],
+				&Self::sorted_members(),
+			);
+			Self::deposit_event(Event::MemberAdded(who));
 			Ok(())
 		}
 
+		/// Remove `who` from the set.
+		///
+		/// May only be called from `T::RemoveOrigin`.
 		#[pallet::call_index(1)]
-		#[pallet::weight(10_000)]
-		pub fn remove_member(origin: OriginFor<T>) -> DispatchResult {
-			let old_member = ensure_signed(origin)?;
+		#[pallet::weight(T::WeightInfo::remove_member(T::MaxMembers::get()))]
+		pub fn remove_member(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::RemoveOrigin::ensure_origin(origin)?;
 
-			ensure!(<Members<T>>::contains_key(&old_member), Error::<T>::NotMember);
+			ensure!(<Members<T>>::contains_key(&who), Error::<T>::NotMember);
 
-			<Members<T>>::remove(&old_member);
+			<Members<T>>::remove(&who);
 			<MemberCount<T>>::mutate(|m| *m -= 1);
-			Self::deposit_event(Event::MemberRemoved(old_member));
+
+			T::MembershipChanged::change_members_sorted(
+				&[],
+				&[who.clone()],
+				&Self::sorted_members(),
+			);
+			Self::deposit_event(Event::MemberRemoved(who));
+			Ok(())
+		}
+
+		/// Replace the member `remove` with `add`. Swapping a member for itself is a no-op.
+		///
+		/// May only be called from `T::SwapOrigin`.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::swap_member(T::MaxMembers::get()))]
+		pub fn swap_member(
+			origin: OriginFor<T>,
+			remove: T::AccountId,
+			add: T::AccountId,
+		) -> DispatchResult {
+			T::SwapOrigin::ensure_origin(origin)?;
+
+			if remove == add {
+				return Ok(())
+			}
+			ensure!(<Members<T>>::contains_key(&remove), Error::<T>::NotMember);
+			ensure!(!<Members<T>>::contains_key(&add), Error::<T>::AlreadyMember);
+
+			<Members<T>>::remove(&remove);
+			<Members<T>>::insert(&add, ());
+
+			T::MembershipChanged::change_members_sorted(
+				&[add.clone()],
+				&[remove.clone()],
+				&Self::sorted_members(),
+			);
+			Self::deposit_event(Event::MembersSwapped(remove, add));
+			Ok(())
+		}
+
+		/// Replace all the members with `members`. Duplicates are ignored.
+		///
+		/// May only be called from `T::ResetOrigin`.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::reset_members(members.len() as u32, T::MaxMembers::get()))]
+		pub fn reset_members(
+			origin: OriginFor<T>,
+			members: BoundedVec<T::AccountId, T::MaxMembers>,
+		) -> DispatchResult {
+			T::ResetOrigin::ensure_origin(origin)?;
+
+			let members: BTreeSet<_> = members.into_iter().collect();
+
+			let old_members = Self::sorted_members();
+			// `MemberCount` tracks the entries in the map, which may be more than `MaxMembers` if
+			// the limit was lowered, so this clears all of them.
+			let _ = <Members<T>>::clear(<MemberCount<T>>::get(), None);
+			for member in &members {
+				<Members<T>>::insert(member, ());
+			}
+			<MemberCount<T>>::put(members.len() as u32);
+
+			// A `BTreeSet` iterates in order, so the new members are already sorted.
+			let new_members: Vec<_> = members.into_iter().collect();
+			T::MembershipChanged::set_members_sorted(&new_members, &old_members);
+			Self::deposit_event(Event::MembersReset);
 			Ok(())
 		}
 	}
 }
 
+impl<T: Config> Pallet<T> {
+	/// All members in ascending order, as `ChangeMembers` expects them.
+	fn sorted_members() -> Vec<T::AccountId> {
+		let mut members: Vec<_> = <Members<T>>::iter_keys().collect();
+		members.sort();
+		members
+	}
+}
+
 impl<T: Config> AccountSet for Pallet<T> {
 	type AccountId = T::AccountId;
 
 	fn accounts() -> BTreeSet<Self::AccountId> {
 		<Members<T>>::iter().map(|(acc, _)| acc).collect()
 	}
+
+	fn contains(who: &Self::AccountId) -> bool {
+		<Members<T>>::contains_key(who)
+	}
+
+	fn count() -> u32 {
+		<MemberCount<T>>::get()
+	}
+
+	/// Members are listed in storage order, which follows the hash of their account.
+	fn accounts_paged(start: Option<&Self::AccountId>, limit: u32) -> Vec<Self::AccountId> {
+		let iter = match start {
+			Some(account) => <Members<T>>::iter_keys_from(<Members<T>>::hashed_key_for(account)),
+			None => <Members<T>>::iter_keys(),
+		};
+		iter.take(limit as usize).collect()
+	}
 }
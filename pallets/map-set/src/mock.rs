@@ -1,10 +1,15 @@
 use crate as pallet_map_set;
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::{
+	ord_parameter_types,
+	traits::{ChangeMembers, ConstU16, ConstU32, ConstU64},
+};
+use frame_system::EnsureSignedBy;
 use sp_core::H256;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
 	BuildStorage,
 };
+use std::cell::RefCell;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -43,8 +48,48 @@ impl frame_system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+ord_parameter_types! {
+	pub const One: u64 = 1;
+	pub const Two: u64 = 2;
+	pub const Three: u64 = 3;
+	pub const Four: u64 = 4;
+}
+
+pub type MaxMembers = ConstU32<16>;
+
+thread_local! {
+	/// The members as last reported through `ChangeMembers`.
+	pub static MEMBERS: RefCell<Vec<u64>> = RefCell::new(vec![]);
+}
+
+/// Follows the set through `ChangeMembers`, checking that every change is consistent.
+pub struct TestChangeMembers;
+impl ChangeMembers<u64> for TestChangeMembers {
+	fn change_members_sorted(incoming: &[u64], outgoing: &[u64], new: &[u64]) {
+		let mut expected = MEMBERS.with(|m| m.borrow().clone());
+		expected.retain(|m| !outgoing.contains(m));
+		expected.extend_from_slice(incoming);
+		expected.sort();
+		assert_eq!(expected, new);
+
+		MEMBERS.with(|m| *m.borrow_mut() = new.to_vec());
+	}
+
+	// Resets replace whatever was reported before, so there is nothing to check against.
+	fn set_members_sorted(new: &[u64], _old: &[u64]) {
+		MEMBERS.with(|m| *m.borrow_mut() = new.to_vec());
+	}
+}
+
 impl pallet_map_set::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type AddOrigin = EnsureSignedBy<One, u64>;
+	type RemoveOrigin = EnsureSignedBy<Two, u64>;
+	type SwapOrigin = EnsureSignedBy<Three, u64>;
+	type ResetOrigin = EnsureSignedBy<Four, u64>;
+	type MaxMembers = MaxMembers;
+	type MembershipChanged = TestChangeMembers;
+	type WeightInfo = ();
 }
 
 // Build genesis storage according to the mock runtime.
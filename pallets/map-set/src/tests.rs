@@ -1,32 +1,48 @@
-use crate::{mock::*, Error, Event, Members, MAX_MEMBERS};
-use frame_support::{assert_err, assert_ok};
+use crate::{mock::*, Error, Event, MemberCount, Members};
+use account_set::{AccountSet, EnsureMember};
+use frame_support::{
+	assert_err, assert_noop, assert_ok, bounded_vec,
+	traits::{EnsureOrigin, Get},
+	BoundedVec,
+};
+use sp_runtime::DispatchError;
 
 #[test]
 fn add_members_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1)));
-		System::assert_last_event(Event::MemberAdded(1).into());
-		assert!(<Members<Test>>::contains_key(1));
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+		System::assert_last_event(Event::MemberAdded(10).into());
+		assert!(<Members<Test>>::contains_key(10));
+		assert_eq!(<MemberCount<Test>>::get(), 1);
+	})
+}
+
+#[test]
+fn add_member_requires_add_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(MapSet::add_member(RuntimeOrigin::signed(10), 10), DispatchError::BadOrigin);
+		assert_noop!(MapSet::add_member(RuntimeOrigin::signed(2), 10), DispatchError::BadOrigin);
 	})
 }
 
 #[test]
 fn cant_add_duplicate_member() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1)));
-		assert_err!(MapSet::add_member(RuntimeOrigin::signed(1)), Error::<Test>::AlreadyMember);
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_err!(MapSet::add_member(RuntimeOrigin::signed(1), 10), Error::<Test>::AlreadyMember);
 	});
 }
 
 #[test]
 fn cant_exceed_max_members() {
 	new_test_ext().execute_with(|| {
-		for i in 0..MAX_MEMBERS {
-			assert_ok!(MapSet::add_member(RuntimeOrigin::signed(i.try_into().unwrap())));
+		let max: u32 = MaxMembers::get();
+		for i in 0..max {
+			assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), i.into()));
 		}
 
 		assert_err!(
-			MapSet::add_member(RuntimeOrigin::signed((MAX_MEMBERS + 1).try_into().unwrap())),
+			MapSet::add_member(RuntimeOrigin::signed(1), (max + 1).into()),
 			Error::<Test>::MembershipLimitReached
 		)
 	})
@@ -35,16 +51,165 @@ fn cant_exceed_max_members() {
 #[test]
 fn remove_member_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1)));
-		assert_ok!(MapSet::remove_member(RuntimeOrigin::signed(1)));
-		System::assert_has_event(Event::MemberRemoved(1).into());
-		assert!(!<Members<Test>>::contains_key(1));
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_noop!(MapSet::remove_member(RuntimeOrigin::signed(1), 10), DispatchError::BadOrigin);
+		assert_ok!(MapSet::remove_member(RuntimeOrigin::signed(2), 10));
+		System::assert_has_event(Event::MemberRemoved(10).into());
+		assert!(!<Members<Test>>::contains_key(10));
+		assert_eq!(<MemberCount<Test>>::get(), 0);
 	})
 }
 
 #[test]
 fn remove_member_handles_error() {
 	new_test_ext().execute_with(|| {
-		assert_err!(MapSet::remove_member(RuntimeOrigin::signed(1)), Error::<Test>::NotMember)
+		assert_err!(MapSet::remove_member(RuntimeOrigin::signed(2), 10), Error::<Test>::NotMember)
+	})
+}
+
+#[test]
+fn swap_member_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 20));
+
+		assert_noop!(
+			MapSet::swap_member(RuntimeOrigin::signed(1), 10, 30),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MapSet::swap_member(RuntimeOrigin::signed(3), 30, 40),
+			Error::<Test>::NotMember
+		);
+		assert_noop!(
+			MapSet::swap_member(RuntimeOrigin::signed(3), 10, 20),
+			Error::<Test>::AlreadyMember
+		);
+
+		// Swapping a member for itself does nothing
+		assert_ok!(MapSet::swap_member(RuntimeOrigin::signed(3), 10, 10));
+		assert!(<Members<Test>>::contains_key(10));
+
+		assert_ok!(MapSet::swap_member(RuntimeOrigin::signed(3), 10, 30));
+		System::assert_last_event(Event::MembersSwapped(10, 30).into());
+		assert!(!<Members<Test>>::contains_key(10));
+		assert!(<Members<Test>>::contains_key(30));
+		assert_eq!(<MemberCount<Test>>::get(), 2);
+	})
+}
+
+#[test]
+fn reset_members_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 20));
+
+		assert_noop!(
+			MapSet::reset_members(RuntimeOrigin::signed(1), bounded_vec![30]),
+			DispatchError::BadOrigin
+		);
+		let max: u32 = MaxMembers::get();
+		let too_many: Vec<u64> = (0..=max).map(u64::from).collect();
+		assert!(BoundedVec::<u64, MaxMembers>::try_from(too_many).is_err());
+
+		assert_ok!(MapSet::reset_members(RuntimeOrigin::signed(4), bounded_vec![30, 20, 30]));
+		System::assert_last_event(Event::MembersReset.into());
+		assert!(!<Members<Test>>::contains_key(10));
+		assert!(<Members<Test>>::contains_key(20));
+		assert!(<Members<Test>>::contains_key(30));
+		assert_eq!(<MemberCount<Test>>::get(), 2);
+	})
+}
+
+#[test]
+fn reset_members_clears_members_over_a_lowered_limit() {
+	new_test_ext().execute_with(|| {
+		// More members than `MaxMembers`, as left behind by an upgrade that lowered the limit
+		let max: u32 = MaxMembers::get();
+		for who in 0..max as u64 + 5 {
+			<Members<Test>>::insert(who, ());
+		}
+		<MemberCount<Test>>::put(max + 5);
+
+		assert_ok!(MapSet::reset_members(RuntimeOrigin::signed(4), bounded_vec![1]));
+		assert_eq!(<Members<Test>>::iter_keys().collect::<Vec<_>>(), vec![1]);
+		assert_eq!(<MemberCount<Test>>::get(), 1);
+	})
+}
+
+#[test]
+fn membership_changes_are_reported() {
+	new_test_ext().execute_with(|| {
+		// `TestChangeMembers` checks that each change matches the previous state
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 20));
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10, 20]);
+
+		assert_ok!(MapSet::swap_member(RuntimeOrigin::signed(3), 20, 5));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![5, 10]);
+
+		assert_ok!(MapSet::remove_member(RuntimeOrigin::signed(2), 5));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10]);
+
+		assert_ok!(MapSet::reset_members(RuntimeOrigin::signed(4), bounded_vec![30, 10, 20]));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10, 20, 30]);
+
+		// Failed calls report nothing
+		assert_noop!(
+			MapSet::add_member(RuntimeOrigin::signed(1), 10),
+			Error::<Test>::AlreadyMember
+		);
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10, 20, 30]);
+	})
+}
+
+#[test]
+fn account_set_lists_members() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MapSet::reset_members(RuntimeOrigin::signed(4), bounded_vec![3, 1, 2]));
+		assert_eq!(<MapSet as AccountSet>::accounts(), [1, 2, 3].into_iter().collect());
+	})
+}
+
+#[test]
+fn account_set_contains_and_counts_members() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(<MapSet as AccountSet>::count(), 0);
+		assert_ok!(MapSet::reset_members(RuntimeOrigin::signed(4), bounded_vec![3, 1, 2]));
+		assert!(<MapSet as AccountSet>::contains(&2));
+		assert!(!<MapSet as AccountSet>::contains(&4));
+		assert_eq!(<MapSet as AccountSet>::count(), 3);
+	})
+}
+
+#[test]
+fn account_set_pages_through_members() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MapSet::reset_members(RuntimeOrigin::signed(4), bounded_vec![1, 2, 3, 4, 5]));
+
+		let mut listed = Vec::new();
+		let mut start = None;
+		loop {
+			let page = <MapSet as AccountSet>::accounts_paged(start.as_ref(), 2);
+			assert!(page.len() <= 2);
+			let Some(last) = page.last().cloned() else { break };
+			listed.extend(page);
+			start = Some(last);
+		}
+
+		listed.sort();
+		assert_eq!(listed, vec![1, 2, 3, 4, 5]);
+		assert_eq!(<MapSet as AccountSet>::accounts_paged(None, 0), Vec::<u64>::new());
+	})
+}
+
+#[test]
+fn ensure_member_accepts_signed_members() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MapSet::add_member(RuntimeOrigin::signed(1), 10));
+
+		assert_eq!(EnsureMember::<MapSet>::try_origin(RuntimeOrigin::signed(10)).ok(), Some(10));
+		assert!(EnsureMember::<MapSet>::try_origin(RuntimeOrigin::signed(11)).is_err());
+		assert!(EnsureMember::<MapSet>::try_origin(RuntimeOrigin::root()).is_err());
 	})
 }
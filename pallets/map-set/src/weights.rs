@@ -0,0 +1,116 @@
+//! Estimated weights for map_set
+//!
+//! Nothing in this file was measured. It was written by hand: the storage reads and writes follow
+//! what each call does, but the execution times and proof sizes are guesses. It is not output of
+//! `benchmark pallet`; replace it with generated weights before relying on it. See
+//! `docs/map-set-vs-vec-set.md`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for map_set.
+pub trait WeightInfo {
+	fn add_member(m: u32, ) -> Weight;
+	fn remove_member(m: u32, ) -> Weight;
+	fn swap_member(m: u32, ) -> Weight;
+	fn reset_members(m: u32, n: u32, ) -> Weight;
+}
+
+/// Hand-written estimates of the weights for map_set; see the module docs.
+pub struct EstimatedWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for EstimatedWeight<T> {
+	/// Storage: MapSet MemberCount (r:1 w:1)
+	/// Storage: MapSet Members (r:m w:1)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn add_member(m: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 1489)
+			// Listing the members for `MembershipChanged`
+			.saturating_add(Weight::from_parts(1_500_000, 2523).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(m.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: MapSet Members (r:m w:1)
+	/// Storage: MapSet MemberCount (r:1 w:1)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn remove_member(m: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 1489)
+			.saturating_add(Weight::from_parts(1_500_000, 2523).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(m.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: MapSet Members (r:m w:2)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn swap_member(m: u32, ) -> Weight {
+		Weight::from_parts(16_000_000, 0)
+			.saturating_add(Weight::from_parts(1_500_000, 2523).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(m.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: MapSet Members (r:n w:m+n)
+	/// Storage: MapSet MemberCount (r:0 w:1)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	/// The range of component `n` is `[0, MaxMembers]`.
+	fn reset_members(m: u32, n: u32, ) -> Weight {
+		// `n` is the number of old members, which are listed and then cleared.
+		Weight::from_parts(15_000_000, 0)
+			.saturating_add(Weight::from_parts(2_000_000, 0).saturating_mul(m.into()))
+			.saturating_add(Weight::from_parts(1_800_000, 2523).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(m.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: MapSet MemberCount (r:1 w:1)
+	/// Storage: MapSet Members (r:m w:1)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn add_member(m: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 1489)
+			.saturating_add(Weight::from_parts(1_500_000, 2523).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(m.into())))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: MapSet Members (r:m w:1)
+	/// Storage: MapSet MemberCount (r:1 w:1)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn remove_member(m: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 1489)
+			.saturating_add(Weight::from_parts(1_500_000, 2523).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(m.into())))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: MapSet Members (r:m w:2)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn swap_member(m: u32, ) -> Weight {
+		Weight::from_parts(16_000_000, 0)
+			.saturating_add(Weight::from_parts(1_500_000, 2523).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(m.into())))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: MapSet Members (r:n w:m+n)
+	/// Storage: MapSet MemberCount (r:0 w:1)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	/// The range of component `n` is `[0, MaxMembers]`.
+	fn reset_members(m: u32, n: u32, ) -> Weight {
+		Weight::from_parts(15_000_000, 0)
+			.saturating_add(Weight::from_parts(2_000_000, 0).saturating_mul(m.into()))
+			.saturating_add(Weight::from_parts(1_800_000, 2523).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(m.into())))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+}
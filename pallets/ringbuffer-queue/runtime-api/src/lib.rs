@@ -0,0 +1,18 @@
+//! Runtime API definition for the ringbuffer-queue pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+pub use ringbuffer_queue::{QueueItem, ValueStruct};
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait RingBufferQueueApi<AccountId, Item> where AccountId: Codec, Item: Codec {
+		/// The number of items in the queue of `owner`.
+		fn len(owner: AccountId) -> u64;
+		/// The item at the start of the queue of `owner`, if any.
+		fn peek(owner: AccountId) -> Option<QueueItem<AccountId, Item>>;
+		/// Up to `limit` items from the start of the queue of `owner`, oldest first.
+		fn items(owner: AccountId, limit: u32) -> Vec<QueueItem<AccountId, Item>>;
+	}
+}
@@ -1,9 +1,19 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use frame_support::{
+	dispatch::DispatchResult,
+	sp_std::{boxed::Box, vec::Vec},
+	storage::with_storage_layer,
+	traits::{EnsureOrigin, Get},
+	weights::Weight,
+};
+use frame_system::{ensure_signed, pallet_prelude::OriginFor};
 pub use pallet::*;
-use ringbuffer::{RingBufferTrait, RingBufferTransient};
+use storage_ringbuffer::{
+	KeyedRingBufferTransient, PushError, Pushed, RingBufferTrait, WrappingOps,
+};
 
-mod ringbuffer;
+pub mod migrations;
 
 #[cfg(test)]
 mod mock;
@@ -11,99 +21,547 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
-#[frame_support::pallet(dev_mode)]
+/// Handles the items that `on_idle` takes off the queues.
+pub trait ProcessItem<AccountId, Item> {
+	/// Whether `on_idle` should drain the queues at all.
+	///
+	/// `()` turns processing off, leaving the queues to be popped by hand.
+	const ENABLED: bool = true;
+
+	/// The most weight that processing a single item may take.
+	fn max_weight() -> Weight;
+
+	/// Process `item`, taken from the queue of `owner`.
+	///
+	/// Storage changes are rolled back if this fails, and the item goes to the owner's dead-letter
+	/// queue.
+	fn process(owner: &AccountId, item: &QueueItem<AccountId, Item>) -> DispatchResult;
+}
+
+impl<AccountId, Item> ProcessItem<AccountId, Item> for () {
+	const ENABLED: bool = false;
+
+	fn max_weight() -> Weight {
+		Weight::zero()
+	}
+
+	fn process(_owner: &AccountId, _item: &QueueItem<AccountId, Item>) -> DispatchResult {
+		Ok(())
+	}
+}
+
+#[frame_support::pallet]
 pub mod pallet {
-	use frame_support::pallet_prelude::*;
+	use super::ProcessItem;
+	use frame_support::{pallet_prelude::*, sp_std::vec::Vec};
 	use frame_system::pallet_prelude::*;
+	pub use storage_ringbuffer::{OverflowPolicy, RingBufferIndex};
 
-	type BufferIndex = u8;
+	/// The current storage version.
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The type of the queued items.
+		type Item: Parameter + Member + MaxEncodedLen;
+
+		/// The type that indexes the items of a queue.
+		///
+		/// A queue holds at most as many items as this type has values, less one: 255 for `u8`,
+		/// 65535 for `u16`.
+		type BufferIndex: Parameter + Member + MaxEncodedLen + RingBufferIndex;
+
+		/// What happens to items added while a queue is full.
+		#[pallet::constant]
+		type OverflowPolicy: Get<OverflowPolicy>;
+
+		/// Origin that may pop from any queue, besides the queue's owner.
+		type ConsumerOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// The most accounts an owner may allow to add items to their queue.
+		#[pallet::constant]
+		type MaxSubmitters: Get<u32>;
+
+		/// Processes queued items with the weight left over at the end of each block.
+		type ItemProcessor: ProcessItem<Self::AccountId, Self::Item>;
 	}
 
-	#[derive(Encode, Decode, Default, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+	/// An example item with an integer and a flag.
+	#[derive(
+		Encode, Decode, MaxEncodedLen, Default, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug,
+	)]
 	pub struct ValueStruct {
 		pub integer: i32,
 		pub boolean: bool,
 	}
 
+	/// A queued value and the account that added it.
+	#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, TypeInfo, RuntimeDebug)]
+	pub struct QueueItem<AccountId, Item> {
+		pub submitter: AccountId,
+		pub value: Item,
+	}
+
+	/// The items queued by this pallet.
+	pub type QueueItemOf<T> =
+		QueueItem<<T as frame_system::Config>::AccountId, <T as Config>::Item>;
+
+	/// The items of every queue, keyed by the queue's owner and the item's index.
 	#[pallet::storage]
 	#[pallet::getter(fn get_value)]
-	pub type BufferMap<T> = StorageMap<_, Blake2_128Concat, BufferIndex, ValueStruct, ValueQuery>;
+	pub type BufferMap<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::BufferIndex,
+		QueueItemOf<T>,
+		OptionQuery,
+	>;
 
 	#[pallet::type_value]
-	pub fn BufferIndexDefaultValue() -> (BufferIndex, BufferIndex) {
-		(0, 0)
+	pub fn BufferIndexDefaultValue<T: Config>() -> (T::BufferIndex, T::BufferIndex) {
+		(0u8.into(), 0u8.into())
 	}
 
+	/// The `(start, end)` range of every queue, keyed by the queue's owner.
 	#[pallet::storage]
 	#[pallet::getter(fn range)]
-	pub type BufferRange<T: Config> =
-		StorageValue<_, (BufferIndex, BufferIndex), ValueQuery, BufferIndexDefaultValue>;
+	pub type BufferRange<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		(T::BufferIndex, T::BufferIndex),
+		ValueQuery,
+		BufferIndexDefaultValue<T>,
+	>;
+
+	/// The accounts besides the owner that may add items to each queue, keyed by the queue's
+	/// owner.
+	#[pallet::storage]
+	pub type Submitters<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<T::AccountId, T::MaxSubmitters>,
+		ValueQuery,
+	>;
+
+	/// Items that failed processing, keyed by the queue's owner and the item's index.
+	#[pallet::storage]
+	pub type DeadLetters<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::BufferIndex,
+		QueueItemOf<T>,
+		OptionQuery,
+	>;
+
+	/// The `(start, end)` range of every dead-letter queue, keyed by the queue's owner.
+	#[pallet::storage]
+	pub type DeadLetterRange<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		(T::BufferIndex, T::BufferIndex),
+		ValueQuery,
+		BufferIndexDefaultValue<T>,
+	>;
+
+	/// The owner of the last queue that `on_idle` fully drained.
+	///
+	/// Processing resumes after it so that every queue gets its turn.
+	#[pallet::storage]
+	pub type ProcessCursor<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub fn deposit_event)]
 	pub enum Event<T: Config> {
-		Popped(i32, bool),
-		DummyEvent(T::AccountId),
+		/// An item was added to a queue. [owner, submitter, index]
+		Pushed(T::AccountId, T::AccountId, T::BufferIndex),
+		/// An item was taken from a queue. [owner, item]
+		Popped(T::AccountId, QueueItemOf<T>),
+		/// A queue was full, so its oldest item was dropped to make room. [owner, item]
+		Evicted(T::AccountId, QueueItemOf<T>),
+		/// A queue was full, so the new item was dropped. [owner, item]
+		Rejected(T::AccountId, QueueItemOf<T>),
+		/// The item at the start of a queue. [owner, item]
+		Peeked(T::AccountId, QueueItemOf<T>),
+		/// An item was processed. [owner, item]
+		Processed(T::AccountId, QueueItemOf<T>),
+		/// Processing an item failed and it was moved to the dead-letter queue. [owner, item,
+		/// error]
+		ProcessingFailed(T::AccountId, QueueItemOf<T>, DispatchError),
+		/// An account may now add items to a queue. [owner, submitter]
+		SubmitterAllowed(T::AccountId, T::AccountId),
+		/// An account may no longer add items to a queue. [owner, submitter]
+		SubmitterRemoved(T::AccountId, T::AccountId),
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		/// Spend leftover block weight on processing queued items.
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			if !T::ItemProcessor::ENABLED {
+				return Weight::zero()
+			}
+			Self::process_queues(remaining_weight)
+		}
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The queue is full and its overflow policy is `Reject`.
+		QueueFull,
+		/// There is nothing in the queue.
+		QueueEmpty,
+		/// Only the queue's owner or the consumer origin may take items from it.
+		NotQueueConsumer,
+		/// Only the queue's owner or the submitters they allowed may add items to it.
+		NotQueueSubmitter,
+		/// The account may already add items to the queue.
+		AlreadySubmitter,
+		/// The owner already allows `MaxSubmitters` accounts.
+		TooManySubmitters,
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
+		/// Add an item to the queue of `owner`.
+		///
+		/// May only be called by `owner` or an account they allowed with `allow_submitter`.
 		#[pallet::call_index(0)]
-		#[pallet::weight(10_000)]
-		pub fn add_to_queue(origin: OriginFor<T>, integer: i32, boolean: bool) -> DispatchResult {
-			let _user = ensure_signed(origin)?;
-			let mut queue = Self::queue_transient();
-			queue.push(ValueStruct { integer, boolean });
-
-			Ok(())
+		#[pallet::weight(Pallet::<T>::push_weight(1))]
+		pub fn add_to_queue(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			item: T::Item,
+		) -> DispatchResult {
+			let submitter = ensure_signed(origin)?;
+			Self::ensure_submitter(&owner, &submitter)?;
+			Self::push_values(owner, submitter, [item])
 		}
 
+		/// Add several items to the queue of `owner`, in order.
+		///
+		/// May only be called by `owner` or an account they allowed with `allow_submitter`.
 		#[pallet::call_index(1)]
-		#[pallet::weight(10_000)]
+		#[pallet::weight(Pallet::<T>::push_weight(items.len() as u32))]
 		pub fn add_multiple(
 			origin: OriginFor<T>,
-			integers: Vec<i32>,
-			boolean: bool,
+			owner: T::AccountId,
+			items: Vec<T::Item>,
 		) -> DispatchResult {
-			let _user = ensure_signed(origin)?;
-			let mut queue = Self::queue_transient();
-			for integer in integers {
-				queue.push(ValueStruct { integer, boolean });
-			}
-			Ok(())
+			let submitter = ensure_signed(origin)?;
+			Self::ensure_submitter(&owner, &submitter)?;
+			Self::push_values(owner, submitter, items)
 		}
 
+		/// Take the oldest item from the queue of `owner`.
+		///
+		/// May only be called by `owner` or from `T::ConsumerOrigin`.
 		#[pallet::call_index(2)]
-		#[pallet::weight(10_000)]
-		pub fn pop_from_queue(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
-			// only a user can pop from the queue
-			let _user = ensure_signed(origin)?;
-
-			let mut queue = Self::queue_transient();
-			if let Some(ValueStruct { integer, boolean }) = queue.pop() {
-				Self::deposit_event(Event::Popped(integer, boolean));
+		#[pallet::weight(Pallet::<T>::pop_weight(1))]
+		pub fn pop_from_queue(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+		) -> DispatchResultWithPostInfo {
+			Self::ensure_consumer(origin, &owner)?;
+
+			let mut queue = Self::queue_transient(owner.clone());
+			if let Some(item) = queue.pop() {
+				Self::deposit_event(Event::Popped(owner.clone(), item));
 			}
 
 			Ok(().into())
 		}
+
+		/// Take up to `n` items from the queue of `owner`, emitting `Popped` for each of them.
+		///
+		/// May only be called by `owner` or from `T::ConsumerOrigin`.
+		#[pallet::call_index(3)]
+		#[pallet::weight(Pallet::<T>::pop_weight(*n))]
+		pub fn pop_multiple(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			n: u32,
+		) -> DispatchResultWithPostInfo {
+			Self::ensure_consumer(origin, &owner)?;
+
+			let mut queue = Self::queue_transient(owner.clone());
+			let popped = queue.pop_n(n.into());
+			let count = popped.len() as u32;
+			for item in popped {
+				Self::deposit_event(Event::Popped(owner.clone(), item));
+			}
+
+			Ok(Some(Self::pop_weight(count)).into())
+		}
+
+		/// Emit `Peeked` with the item at the start of the queue of `owner`, leaving it in place.
+		#[pallet::call_index(4)]
+		#[pallet::weight(Pallet::<T>::peek_weight())]
+		pub fn peek(origin: OriginFor<T>, owner: T::AccountId) -> DispatchResult {
+			ensure_signed(origin)?;
+
+			let item = Self::peek_item(owner.clone()).ok_or(Error::<T>::QueueEmpty)?;
+			Self::deposit_event(Event::Peeked(owner, item));
+			Ok(())
+		}
+
+		/// Allow `submitter` to add items to the caller's queue.
+		#[pallet::call_index(5)]
+		#[pallet::weight(Pallet::<T>::submitters_weight())]
+		pub fn allow_submitter(origin: OriginFor<T>, submitter: T::AccountId) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+
+			<Submitters<T>>::try_mutate(&owner, |submitters| {
+				ensure!(!submitters.contains(&submitter), Error::<T>::AlreadySubmitter);
+				submitters
+					.try_push(submitter.clone())
+					.map_err(|_| Error::<T>::TooManySubmitters)
+			})?;
+			Self::deposit_event(Event::SubmitterAllowed(owner, submitter));
+			Ok(())
+		}
+
+		/// Stop `submitter` from adding items to the caller's queue. Items it already added stay
+		/// queued.
+		#[pallet::call_index(6)]
+		#[pallet::weight(Pallet::<T>::submitters_weight())]
+		pub fn remove_submitter(origin: OriginFor<T>, submitter: T::AccountId) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+
+			<Submitters<T>>::try_mutate_exists(&owner, |maybe_submitters| {
+				let submitters = maybe_submitters.as_mut().ok_or(Error::<T>::NotQueueSubmitter)?;
+				let index = submitters
+					.iter()
+					.position(|allowed| allowed == &submitter)
+					.ok_or(Error::<T>::NotQueueSubmitter)?;
+				submitters.swap_remove(index);
+				if submitters.is_empty() {
+					*maybe_submitters = None;
+				}
+				Ok::<_, Error<T>>(())
+			})?;
+			Self::deposit_event(Event::SubmitterRemoved(owner, submitter));
+			Ok(())
+		}
 	}
 }
 
+type Queue<T> = Box<dyn RingBufferTrait<QueueItemOf<T>>>;
+
 impl<T: Config> Pallet<T> {
 	/// Constructor function so we don't have to specify the types every time.
 	///
-	/// Constructs a ringbuffer transient and returns it as a boxed trait object.
+	/// Constructs a ringbuffer transient over the queue of `owner` and returns it as a boxed
+	/// trait object.
 	/// See [this part of the Rust book](https://doc.rust-lang.org/book/ch17-02-trait-objects.html#trait-objects-perform-dynamic-dispatch)
-	fn queue_transient() -> Box<dyn RingBufferTrait<ValueStruct>> {
-		Box::new(
-			RingBufferTransient::<ValueStruct, pallet::BufferRange<T>, pallet::BufferMap<T>, u8>::new(),
-		)
+	fn queue_transient(owner: T::AccountId) -> Queue<T> {
+		Box::new(KeyedRingBufferTransient::<
+			T::AccountId,
+			QueueItemOf<T>,
+			pallet::BufferRange<T>,
+			pallet::BufferMap<T>,
+			T::BufferIndex,
+		>::with_policy(owner, T::OverflowPolicy::get()))
+	}
+
+	/// Check that `origin` may take items from the queue of `owner`.
+	fn ensure_consumer(origin: OriginFor<T>, owner: &T::AccountId) -> DispatchResult {
+		match T::ConsumerOrigin::try_origin(origin) {
+			Ok(_) => Ok(()),
+			Err(origin) => {
+				let who = ensure_signed(origin)?;
+				frame_support::ensure!(&who == owner, Error::<T>::NotQueueConsumer);
+				Ok(())
+			},
+		}
+	}
+
+	/// Check that `submitter` may add items to the queue of `owner`.
+	fn ensure_submitter(owner: &T::AccountId, submitter: &T::AccountId) -> DispatchResult {
+		frame_support::ensure!(
+			submitter == owner || <Submitters<T>>::get(owner).contains(submitter),
+			Error::<T>::NotQueueSubmitter
+		);
+		Ok(())
+	}
+
+	/// Push `values` from `submitter` onto the queue of `owner`, reporting every item stored and
+	/// anything that overflowed.
+	///
+	/// Fails with `QueueFull` if the queue rejects an item.
+	fn push_values(
+		owner: T::AccountId,
+		submitter: T::AccountId,
+		values: impl IntoIterator<Item = T::Item>,
+	) -> DispatchResult {
+		let mut queue = Self::queue_transient(owner.clone());
+		// Every stored item lands at the end of the queue, which only moves when one is stored.
+		let (_, mut end) = Self::range(&owner);
+		for value in values {
+			let item = QueueItem { submitter: submitter.clone(), value };
+			match queue.push(item) {
+				Ok(Pushed::Stored) => {},
+				Ok(Pushed::Evicted(evicted)) =>
+					Self::deposit_event(Event::Evicted(owner.clone(), evicted)),
+				Ok(Pushed::Dropped(dropped)) => {
+					Self::deposit_event(Event::Rejected(owner.clone(), dropped));
+					continue
+				},
+				Err(PushError::Full) => return Err(Error::<T>::QueueFull.into()),
+			}
+			Self::deposit_event(Event::Pushed(owner.clone(), submitter.clone(), end));
+			end = end.wrapping_add(1u8.into());
+		}
+		Ok(())
+	}
+
+	/// A ringbuffer transient over the dead-letter queue of `owner`.
+	///
+	/// When it is full the oldest failure makes room for the newest.
+	fn dead_letter_transient(owner: T::AccountId) -> Queue<T> {
+		Box::new(KeyedRingBufferTransient::<
+			T::AccountId,
+			QueueItemOf<T>,
+			pallet::DeadLetterRange<T>,
+			pallet::DeadLetters<T>,
+			T::BufferIndex,
+		>::new(owner))
+	}
+
+	/// Process queued items, one queue after the other, until `limit` would be exceeded.
+	///
+	/// Returns the weight used.
+	fn process_queues(limit: Weight) -> Weight {
+		let db = T::DbWeight::get();
+		// Reading and updating the cursor.
+		let mut used = db.reads_writes(1, 1);
+		// Reading the range of a queue and writing it back.
+		let per_queue = db.reads_writes(1, 1);
+		let per_item = Self::process_weight();
+		if limit.any_lt(used.saturating_add(per_queue).saturating_add(per_item)) {
+			return Weight::zero()
+		}
+
+		let mut cursor = <ProcessCursor<T>>::get();
+		// Empty queues keep no range, so only owners with items are visited.
+		let owners = match &cursor {
+			Some(owner) =>
+				<BufferRange<T>>::iter_keys_from(<BufferRange<T>>::hashed_key_for(owner)),
+			None => <BufferRange<T>>::iter_keys(),
+		};
+
+		let mut finished = true;
+		for owner in owners {
+			if limit.any_lt(used.saturating_add(per_queue).saturating_add(per_item)) {
+				finished = false;
+				break
+			}
+			used.saturating_accrue(per_queue);
+
+			let drained = loop {
+				let mut queue = Self::queue_transient(owner.clone());
+				if queue.is_empty() {
+					break true
+				}
+				if limit.any_lt(used.saturating_add(per_item)) {
+					break false
+				}
+				used.saturating_accrue(per_item);
+				// Commit the pop before processing the item, so that a processor pushing to this
+				// same queue doesn't have its items overwritten by a stale range.
+				let item = queue.pop();
+				drop(queue);
+				if let Some(item) = item {
+					Self::process_item(&owner, item);
+				}
+			};
+			if !drained {
+				// Out of weight: start from this queue again next time.
+				finished = false;
+				break
+			}
+			cursor = Some(owner);
+		}
+
+		match cursor {
+			Some(owner) if !finished => <ProcessCursor<T>>::put(owner),
+			_ => <ProcessCursor<T>>::kill(),
+		}
+		used
+	}
+
+	/// Hand `item` to the processor, moving it to the dead-letter queue if that fails.
+	fn process_item(owner: &T::AccountId, item: QueueItemOf<T>) {
+		match with_storage_layer(|| T::ItemProcessor::process(owner, &item)) {
+			Ok(()) => Self::deposit_event(Event::Processed(owner.clone(), item)),
+			Err(error) => {
+				let mut dead_letters = Self::dead_letter_transient(owner.clone());
+				// Overwriting never fails; a full dead-letter queue loses its oldest entry.
+				let _ = dead_letters.push(item.clone());
+				Self::deposit_event(Event::ProcessingFailed(owner.clone(), item, error));
+			},
+		}
+	}
+
+	/// Weight of processing one item: taking it from its queue and writing back the range, the
+	/// processor itself, and possibly moving it to the dead-letter queue.
+	pub fn process_weight() -> Weight {
+		T::ItemProcessor::max_weight().saturating_add(T::DbWeight::get().reads_writes(3, 5))
+	}
+
+	/// Weight of pushing `n` items: the submitters are read, each item is written and may evict
+	/// another, and the range is read and written once.
+	pub fn push_weight(n: u32) -> Weight {
+		let n = u64::from(n);
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(2 + n, 1 + 2 * n))
+	}
+
+	/// Weight of changing who may add items to a queue: the submitters are read and written.
+	pub fn submitters_weight() -> Weight {
+		Weight::from_parts(10_000, 0).saturating_add(T::DbWeight::get().reads_writes(1, 1))
+	}
+
+	/// Weight of popping `n` items: each one is read and removed.
+	pub fn pop_weight(n: u32) -> Weight {
+		let n = u64::from(n);
+		Weight::from_parts(10_000, 0).saturating_add(T::DbWeight::get().reads_writes(1 + n, 1 + n))
+	}
+
+	/// Weight of peeking: the range and the first item are read.
+	pub fn peek_weight() -> Weight {
+		Weight::from_parts(10_000, 0).saturating_add(T::DbWeight::get().reads(2))
+	}
+
+	/// The number of items in the queue of `owner`.
+	pub fn queue_len(owner: T::AccountId) -> u64 {
+		Self::queue_transient(owner).len()
+	}
+
+	/// The item at the start of the queue of `owner`, if any.
+	pub fn peek_item(owner: T::AccountId) -> Option<QueueItemOf<T>> {
+		Self::queue_transient(owner).peek()
+	}
+
+	/// Up to `limit` items from the start of the queue of `owner`, oldest first.
+	pub fn queued_items(owner: T::AccountId, limit: u32) -> Vec<QueueItemOf<T>> {
+		Self::queue_transient(owner).iter().take(limit as usize).collect()
+	}
+
+	/// Up to `limit` items of the dead-letter queue of `owner`, oldest first.
+	pub fn dead_letters(owner: T::AccountId, limit: u32) -> Vec<QueueItemOf<T>> {
+		Self::dead_letter_transient(owner).iter().take(limit as usize).collect()
 	}
 }
@@ -0,0 +1,92 @@
+//! Storage migrations for the ringbuffer-queue pallet.
+
+use super::*;
+use codec::Encode;
+use frame_support::{
+	pallet_prelude::*,
+	sp_std::marker::PhantomData,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{GetStorageVersion, OnRuntimeUpgrade},
+	Blake2_128Concat, StorageHasher,
+};
+
+#[cfg(feature = "try-runtime")]
+use frame_support::sp_runtime::TryRuntimeError;
+
+const LOG_TARGET: &str = "runtime::ringbuffer-queue";
+
+pub mod v1 {
+	use super::*;
+
+	/// Clears the single queue kept before queues were owned by accounts.
+	///
+	/// That queue had its `(u8, u8)` range in a `BufferRange` value and its items in a
+	/// `BufferMap` keyed by a `u8` index, under the same prefixes as today's maps. Its items
+	/// had no owner or submitter to move them under, so they are dropped; at most 256 keys are
+	/// removed. Queues stored by this version are left alone, so the migration doesn't cover
+	/// changing `BufferIndex` on a chain that already has per-owner queues.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> MigrateToV1<T> {
+		/// The key of the old range.
+		fn old_range_key() -> Vec<u8> {
+			BufferRange::<T>::final_prefix().to_vec()
+		}
+
+		/// The keys of every item the old queue may have held.
+		fn old_item_keys() -> impl Iterator<Item = Vec<u8>> {
+			let prefix = BufferMap::<T>::final_prefix();
+			(0..=u8::MAX).map(move |index| {
+				let mut key = prefix.to_vec();
+				key.extend(Blake2_128Concat::hash(&index.encode()));
+				key
+			})
+		}
+	}
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"skipping migration to v1, storage is already at {:?}",
+					on_chain
+				);
+				return T::DbWeight::get().reads(1)
+			}
+
+			unhashed::kill(&Self::old_range_key());
+			let mut dropped = 0u32;
+			for key in Self::old_item_keys() {
+				if unhashed::exists(&key) {
+					unhashed::kill(&key);
+					dropped += 1;
+				}
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "dropped {} items of the unowned queue", dropped);
+			T::DbWeight::get().reads_writes(257, 2 + u64::from(dropped))
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			Ok(Vec::new())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"storage version should be 1 after the migration"
+			);
+			ensure!(!unhashed::exists(&Self::old_range_key()), "the old range should be removed");
+			ensure!(
+				Self::old_item_keys().all(|key| !unhashed::exists(&key)),
+				"the old items should be removed"
+			);
+			Ok(())
+		}
+	}
+}
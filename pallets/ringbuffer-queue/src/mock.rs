@@ -1,10 +1,19 @@
 use crate as pallet_ringbuffer;
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::{
+	assert_ok,
+	dispatch::DispatchResult,
+	ord_parameter_types, parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64},
+	weights::Weight,
+};
+use frame_system::EnsureSignedBy;
+use pallet_ringbuffer::{OverflowPolicy, ProcessItem, QueueItem, ValueStruct};
 use sp_core::H256;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
 	BuildStorage,
 };
+use std::cell::RefCell;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -43,14 +52,69 @@ impl frame_system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+ord_parameter_types! {
+	pub const Consumer: u64 = 100;
+}
+
+parameter_types! {
+	pub static Policy: OverflowPolicy = OverflowPolicy::Overwrite;
+}
+
 impl pallet_ringbuffer::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type Item = ValueStruct;
+	type BufferIndex = u8;
+	type OverflowPolicy = Policy;
+	type ConsumerOrigin = EnsureSignedBy<Consumer, u64>;
+	type MaxSubmitters = ConstU32<2>;
+	type ItemProcessor = TestProcessor;
+}
+
+thread_local! {
+	/// Every item processed successfully, with the owner of its queue.
+	pub static PROCESSED: RefCell<Vec<(u64, QueueItem<u64, ValueStruct>)>> = RefCell::new(Vec::new());
+}
+
+/// Weight of processing a single item in the tests.
+pub const PROCESS_WEIGHT: u64 = 1_000;
+
+/// Fails on negative integers and records everything else in `PROCESSED`.
+///
+/// Integers of 100 or more are worked off in steps: what is left over 100 is queued again.
+pub struct TestProcessor;
+impl ProcessItem<u64, ValueStruct> for TestProcessor {
+	fn max_weight() -> Weight {
+		Weight::from_parts(PROCESS_WEIGHT, 0)
+	}
+
+	fn process(owner: &u64, item: &QueueItem<u64, ValueStruct>) -> DispatchResult {
+		frame_support::ensure!(item.value.integer >= 0, "negative integer");
+		if item.value.integer >= 100 {
+			let rest = ValueStruct { integer: item.value.integer - 100, ..item.value.clone() };
+			RingBuffer::add_to_queue(RuntimeOrigin::signed(*owner), *owner, rest)?;
+		}
+		PROCESSED.with(|processed| processed.borrow_mut().push((*owner, item.clone())));
+		Ok(())
+	}
+}
+
+/// The integers processed so far, in order.
+pub fn processed_integers() -> Vec<i32> {
+	PROCESSED
+		.with(|processed| processed.borrow().iter().map(|(_, item)| item.value.integer).collect())
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
 	let t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
 	let mut ext = sp_io::TestExternalities::new(t);
-	ext.execute_with(|| System::set_block_number(1));
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		// Account 2 submits to the queues the tests use.
+		for owner in [1, 3, 4] {
+			assert_ok!(RingBuffer::allow_submitter(RuntimeOrigin::signed(owner), 2));
+		}
+		System::reset_events();
+	});
 	ext
 }
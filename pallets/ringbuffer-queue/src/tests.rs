@@ -1,34 +1,430 @@
-use crate::{mock::*, Event, ValueStruct};
-use frame_support::assert_ok;
+use crate::{
+	migrations, mock::*, BufferMap, BufferRange, Error, Event, OverflowPolicy, ProcessCursor,
+	QueueItem, Submitters, ValueStruct,
+};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{GetStorageVersion, Hooks, OnRuntimeUpgrade, StorageVersion},
+	weights::Weight,
+	Blake2_128Concat, StorageHasher,
+};
+use sp_runtime::DispatchError;
+
+const OWNER: u64 = 1;
+const SUBMITTER: u64 = 2;
+
+fn value(integer: i32, boolean: bool) -> ValueStruct {
+	ValueStruct { integer, boolean }
+}
+
+fn values(integers: impl IntoIterator<Item = i32>, boolean: bool) -> Vec<ValueStruct> {
+	integers.into_iter().map(|integer| value(integer, boolean)).collect()
+}
+
+fn item(submitter: u64, integer: i32, boolean: bool) -> QueueItem<u64, ValueStruct> {
+	QueueItem { submitter, value: value(integer, boolean) }
+}
 
 #[test]
 fn add_to_queue_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(RingBuffer::add_to_queue(RuntimeOrigin::signed(1), 1, true));
-		assert_eq!(RingBuffer::get_value(0), ValueStruct { integer: 1, boolean: true });
-		assert_eq!(RingBuffer::range(), (0, 1));
+		assert_ok!(RingBuffer::add_to_queue(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			value(1, true)
+		));
+		assert_eq!(RingBuffer::get_value(OWNER, 0u8), Some(item(SUBMITTER, 1, true)));
+		assert_eq!(RingBuffer::range(OWNER), (0, 1));
+		System::assert_last_event(Event::<Test>::Pushed(OWNER, SUBMITTER, 0).into());
 	})
 }
 
 #[test]
 fn add_multiple_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(RingBuffer::add_multiple(RuntimeOrigin::signed(1), vec![1, 2, 3], true));
-		assert_eq!(RingBuffer::get_value(0), ValueStruct { integer: 1, boolean: true });
-		assert_eq!(RingBuffer::range(), (0, 3));
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, 2, 3], true)
+		));
+		assert_eq!(RingBuffer::get_value(OWNER, 0u8), Some(item(SUBMITTER, 1, true)));
+		assert_eq!(RingBuffer::range(OWNER), (0, 3));
+		System::assert_has_event(Event::<Test>::Pushed(OWNER, SUBMITTER, 1).into());
+		System::assert_last_event(Event::<Test>::Pushed(OWNER, SUBMITTER, 2).into());
+	})
+}
+
+#[test]
+fn only_allowed_submitters_may_push() {
+	new_test_ext().execute_with(|| {
+		const STRANGER: u64 = 5;
+		assert_noop!(
+			RingBuffer::add_to_queue(RuntimeOrigin::signed(STRANGER), OWNER, value(1, true)),
+			Error::<Test>::NotQueueSubmitter
+		);
+		assert_noop!(
+			RingBuffer::add_multiple(RuntimeOrigin::signed(STRANGER), OWNER, values(vec![1], true)),
+			Error::<Test>::NotQueueSubmitter
+		);
+
+		// Owners may always push to their own queue
+		assert_ok!(RingBuffer::add_to_queue(RuntimeOrigin::signed(OWNER), OWNER, value(1, true)));
+
+		assert_ok!(RingBuffer::allow_submitter(RuntimeOrigin::signed(OWNER), STRANGER));
+		System::assert_last_event(Event::<Test>::SubmitterAllowed(OWNER, STRANGER).into());
+		assert_ok!(RingBuffer::add_to_queue(
+			RuntimeOrigin::signed(STRANGER),
+			OWNER,
+			value(2, true)
+		));
+
+		assert_ok!(RingBuffer::remove_submitter(RuntimeOrigin::signed(OWNER), STRANGER));
+		System::assert_last_event(Event::<Test>::SubmitterRemoved(OWNER, STRANGER).into());
+		assert_noop!(
+			RingBuffer::add_to_queue(RuntimeOrigin::signed(STRANGER), OWNER, value(3, true)),
+			Error::<Test>::NotQueueSubmitter
+		);
+		assert_eq!(RingBuffer::queue_len(OWNER), 2);
+	})
+}
+
+#[test]
+fn submitters_are_limited() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			RingBuffer::allow_submitter(RuntimeOrigin::signed(OWNER), SUBMITTER),
+			Error::<Test>::AlreadySubmitter
+		);
+		assert_ok!(RingBuffer::allow_submitter(RuntimeOrigin::signed(OWNER), 5));
+		assert_noop!(
+			RingBuffer::allow_submitter(RuntimeOrigin::signed(OWNER), 6),
+			Error::<Test>::TooManySubmitters
+		);
+
+		assert_noop!(
+			RingBuffer::remove_submitter(RuntimeOrigin::signed(OWNER), 6),
+			Error::<Test>::NotQueueSubmitter
+		);
+		assert_ok!(RingBuffer::remove_submitter(RuntimeOrigin::signed(OWNER), 5));
+		assert_ok!(RingBuffer::remove_submitter(RuntimeOrigin::signed(OWNER), SUBMITTER));
+		assert!(!Submitters::<Test>::contains_key(OWNER));
+	})
+}
+
+#[test]
+fn queues_are_kept_per_owner() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_to_queue(RuntimeOrigin::signed(SUBMITTER), 1, value(1, true)));
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			3,
+			values(vec![2, 3], true)
+		));
+
+		assert_eq!(RingBuffer::queue_len(1), 1);
+		assert_eq!(RingBuffer::queue_len(3), 2);
+		assert_ok!(RingBuffer::pop_from_queue(RuntimeOrigin::signed(1), 1));
+		assert_eq!(RingBuffer::queue_len(1), 0);
+		assert_eq!(RingBuffer::queue_len(3), 2);
 	})
 }
 
 #[test]
 fn pop_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(RingBuffer::add_to_queue(RuntimeOrigin::signed(1), 1, true));
-		assert_eq!(RingBuffer::get_value(0), ValueStruct { integer: 1, boolean: true });
-		assert_eq!(RingBuffer::range(), (0, 1));
+		assert_ok!(RingBuffer::add_to_queue(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			value(1, true)
+		));
+		assert_eq!(RingBuffer::range(OWNER), (0, 1));
+
+		assert_ok!(RingBuffer::pop_from_queue(RuntimeOrigin::signed(OWNER), OWNER));
+		// An empty queue keeps no range
+		assert!(!BufferRange::<Test>::contains_key(OWNER));
+		assert_eq!(RingBuffer::get_value(OWNER, 0u8), None);
+
+		System::assert_last_event(Event::<Test>::Popped(OWNER, item(SUBMITTER, 1, true)).into());
+	})
+}
+
+#[test]
+fn only_owner_or_consumer_can_pop() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, 2], true)
+		));
+
+		assert_noop!(
+			RingBuffer::pop_from_queue(RuntimeOrigin::signed(SUBMITTER), OWNER),
+			Error::<Test>::NotQueueConsumer
+		);
+		assert_noop!(
+			RingBuffer::pop_multiple(RuntimeOrigin::signed(SUBMITTER), OWNER, 2),
+			Error::<Test>::NotQueueConsumer
+		);
+		assert_noop!(
+			RingBuffer::pop_from_queue(RuntimeOrigin::root(), OWNER),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(RingBuffer::pop_from_queue(RuntimeOrigin::signed(Consumer::get()), OWNER));
+		System::assert_last_event(Event::<Test>::Popped(OWNER, item(SUBMITTER, 1, true)).into());
+	})
+}
+
+/// Fill the queue of `OWNER` up to its capacity of 255 items.
+fn fill_queue() {
+	assert_ok!(RingBuffer::add_multiple(
+		RuntimeOrigin::signed(SUBMITTER),
+		OWNER,
+		values(0..255, true)
+	));
+	assert_eq!(RingBuffer::range(OWNER), (0, 255));
+}
+
+#[test]
+fn overwrite_evicts_oldest_item() {
+	new_test_ext().execute_with(|| {
+		fill_queue();
+		assert_ok!(RingBuffer::add_to_queue(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			value(255, false)
+		));
+		System::assert_has_event(Event::<Test>::Evicted(OWNER, item(SUBMITTER, 0, true)).into());
+		System::assert_last_event(Event::<Test>::Pushed(OWNER, SUBMITTER, 255).into());
+		assert_eq!(RingBuffer::range(OWNER), (1, 0));
+	})
+}
+
+#[test]
+fn reject_fails_when_full() {
+	new_test_ext().execute_with(|| {
+		Policy::set(OverflowPolicy::Reject);
+		fill_queue();
+		assert_noop!(
+			RingBuffer::add_to_queue(RuntimeOrigin::signed(SUBMITTER), OWNER, value(255, false)),
+			Error::<Test>::QueueFull
+		);
+		// A batch that doesn't fit is refused as a whole.
+		assert_ok!(RingBuffer::pop_from_queue(RuntimeOrigin::signed(OWNER), OWNER));
+		assert_noop!(
+			RingBuffer::add_multiple(
+				RuntimeOrigin::signed(SUBMITTER),
+				OWNER,
+				values(vec![1, 2], false)
+			),
+			Error::<Test>::QueueFull
+		);
+	})
+}
+
+#[test]
+fn drop_newest_reports_rejected_items() {
+	new_test_ext().execute_with(|| {
+		Policy::set(OverflowPolicy::DropNewest);
+		fill_queue();
+		assert_ok!(RingBuffer::add_to_queue(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			value(255, false)
+		));
+		System::assert_last_event(
+			Event::<Test>::Rejected(OWNER, item(SUBMITTER, 255, false)).into(),
+		);
+		assert_eq!(RingBuffer::range(OWNER), (0, 255));
+		assert_eq!(RingBuffer::get_value(OWNER, 0u8), Some(item(SUBMITTER, 0, true)));
+	})
+}
+
+#[test]
+fn pop_multiple_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, 2, 3], true)
+		));
+
+		assert_ok!(RingBuffer::pop_multiple(RuntimeOrigin::signed(OWNER), OWNER, 2));
+		assert_eq!(RingBuffer::range(OWNER), (2, 3));
+		System::assert_has_event(Event::<Test>::Popped(OWNER, item(SUBMITTER, 1, true)).into());
+		System::assert_last_event(Event::<Test>::Popped(OWNER, item(SUBMITTER, 2, true)).into());
+
+		// Asking for more than is queued pops what there is.
+		assert_ok!(RingBuffer::pop_multiple(RuntimeOrigin::signed(OWNER), OWNER, 5));
+		System::assert_last_event(Event::<Test>::Popped(OWNER, item(SUBMITTER, 3, true)).into());
+		assert_eq!(RingBuffer::queue_len(OWNER), 0);
+	})
+}
+
+#[test]
+fn peek_works() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			RingBuffer::peek(RuntimeOrigin::signed(SUBMITTER), OWNER),
+			Error::<Test>::QueueEmpty
+		);
+
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, 2], false)
+		));
+		assert_ok!(RingBuffer::peek(RuntimeOrigin::signed(SUBMITTER), OWNER));
+		System::assert_last_event(Event::<Test>::Peeked(OWNER, item(SUBMITTER, 1, false)).into());
+		assert_eq!(RingBuffer::range(OWNER), (0, 2));
+	})
+}
+
+#[test]
+fn queue_can_be_inspected() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(RingBuffer::peek_item(OWNER), None);
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, 2, 3], true)
+		));
+
+		assert_eq!(RingBuffer::queue_len(OWNER), 3);
+		assert_eq!(RingBuffer::peek_item(OWNER), Some(item(SUBMITTER, 1, true)));
+		let integers: Vec<_> = RingBuffer::queued_items(OWNER, 2)
+			.into_iter()
+			.map(|item| item.value.integer)
+			.collect();
+		assert_eq!(integers, vec![1, 2]);
+	})
+}
+
+/// Run `on_idle` with room for processing `items` items.
+fn idle_for(items: u64) -> Weight {
+	RingBuffer::on_idle(1, Weight::from_parts(items * PROCESS_WEIGHT, 0))
+}
+
+#[test]
+fn on_idle_processes_items_within_weight() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, 2, 3], true)
+		));
+
+		assert_eq!(idle_for(2), Weight::from_parts(2 * PROCESS_WEIGHT, 0));
+		assert_eq!(processed_integers(), vec![1, 2]);
+		assert_eq!(RingBuffer::queue_len(OWNER), 1);
+		System::assert_last_event(Event::<Test>::Processed(OWNER, item(SUBMITTER, 2, true)).into());
+
+		// Not even one item fits, so nothing is done.
+		assert_eq!(
+			RingBuffer::on_idle(1, Weight::from_parts(PROCESS_WEIGHT - 1, 0)),
+			Weight::zero()
+		);
+
+		idle_for(10);
+		assert_eq!(processed_integers(), vec![1, 2, 3]);
+		assert_eq!(RingBuffer::queue_len(OWNER), 0);
+		// Drained queues aren't visited again
+		assert_eq!(BufferRange::<Test>::iter_keys().count(), 0);
+	})
+}
+
+#[test]
+fn processor_can_push_to_the_queue_it_processes() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![150, 1], true)
+		));
+
+		// Processing 150 queues 50 behind 1
+		idle_for(10);
+		assert_eq!(processed_integers(), vec![150, 1, 50]);
+		assert_eq!(RingBuffer::queue_len(OWNER), 0);
+	})
+}
+
+#[test]
+fn on_idle_takes_turns_between_queues() {
+	new_test_ext().execute_with(|| {
+		for owner in [1, 3, 4] {
+			assert_ok!(RingBuffer::add_multiple(
+				RuntimeOrigin::signed(SUBMITTER),
+				owner,
+				values(vec![owner as i32; 2], true)
+			));
+		}
+
+		// Three blocks with room for two items each drain one queue each, in storage order.
+		for _ in 0..3 {
+			idle_for(2);
+		}
+		let mut integers = processed_integers();
+		assert_eq!(integers.len(), 6);
+		integers.dedup();
+		assert_eq!(integers.len(), 3, "each queue should be drained in one go");
+		assert_eq!(ProcessCursor::<Test>::get(), None);
+	})
+}
+
+#[test]
+fn failed_items_go_to_dead_letters() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_multiple(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			values(vec![1, -2, 3], true)
+		));
+
+		idle_for(3);
+		assert_eq!(processed_integers(), vec![1, 3]);
+		assert_eq!(RingBuffer::dead_letters(OWNER, 10), vec![item(SUBMITTER, -2, true)]);
+		System::assert_has_event(
+			Event::<Test>::ProcessingFailed(
+				OWNER,
+				item(SUBMITTER, -2, true),
+				"negative integer".into(),
+			)
+			.into(),
+		);
+		assert_eq!(RingBuffer::queue_len(OWNER), 0);
+	})
+}
+
+#[test]
+fn migration_to_v1_clears_the_unowned_queue() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(RingBuffer::add_to_queue(
+			RuntimeOrigin::signed(SUBMITTER),
+			OWNER,
+			value(1, true)
+		));
+
+		// The single queue as it was stored before queues had owners
+		unhashed::put(&BufferRange::<Test>::final_prefix(), &(0u8, 2u8));
+		let old_item_key = |index: u8| {
+			let mut key = BufferMap::<Test>::final_prefix().to_vec();
+			key.extend(Blake2_128Concat::hash(&index.encode()));
+			key
+		};
+		for index in 0..2u8 {
+			unhashed::put(&old_item_key(index), &value(index.into(), false));
+		}
+		StorageVersion::new(0).put::<RingBuffer>();
 
-		assert_ok!(RingBuffer::pop_from_queue(RuntimeOrigin::signed(1)));
-		assert_eq!(RingBuffer::range(), (1, 1));
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
 
-		System::assert_last_event(Event::<Test>::Popped(1, true).into());
+		assert_eq!(RingBuffer::on_chain_storage_version(), 1);
+		assert!(!unhashed::exists(&BufferRange::<Test>::final_prefix()));
+		assert!((0..2u8).all(|index| !unhashed::exists(&old_item_key(index))));
+		// Owned queues are untouched
+		assert_eq!(RingBuffer::queued_items(OWNER, 10), vec![item(SUBMITTER, 1, true)]);
 	})
 }
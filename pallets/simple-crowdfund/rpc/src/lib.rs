@@ -0,0 +1,100 @@
+//! RPC interface for the simple-crowdfund pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+pub use simple_crowdfund_runtime_api::SimpleCrowdfundApi as SimpleCrowdfundRuntimeApi;
+use simple_crowdfund_runtime_api::{FundIndex, FundInfo, FundStatus};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+
+#[rpc(client, server)]
+pub trait SimpleCrowdfundApi<BlockHash, AccountId, Balance, BlockNumber> {
+	/// Up to `limit` contributors to the fund at `index` with their contributions, resuming
+	/// after the `start_key` account.
+	#[method(name = "crowdfund_contributions")]
+	fn contributions(
+		&self,
+		index: FundIndex,
+		start_key: Option<AccountId>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(AccountId, Balance)>>;
+
+	/// The fund at `index` with its current status, if it exists.
+	#[method(name = "crowdfund_fundInfo")]
+	fn fund_info(
+		&self,
+		index: FundIndex,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<(FundInfo<AccountId, Balance, BlockNumber>, FundStatus)>>;
+}
+
+/// Provides RPC methods to query the simple-crowdfund pallet's funds and their backers.
+pub struct SimpleCrowdfund<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> SimpleCrowdfund<C, Block> {
+	/// Create new `SimpleCrowdfund` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block, AccountId, Balance, BlockNumber>
+	SimpleCrowdfundApiServer<<Block as BlockT>::Hash, AccountId, Balance, BlockNumber>
+	for SimpleCrowdfund<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: SimpleCrowdfundRuntimeApi<Block, AccountId, Balance, BlockNumber>,
+	AccountId: Codec + Send + Sync + 'static,
+	Balance: Codec + Send + Sync + 'static,
+	BlockNumber: Codec + Send + Sync + 'static,
+{
+	fn contributions(
+		&self,
+		index: FundIndex,
+		start_key: Option<AccountId>,
+		limit: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(AccountId, Balance)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.contributions(at, index, start_key, limit)
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn fund_info(
+		&self,
+		index: FundIndex,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<(FundInfo<AccountId, Balance, BlockNumber>, FundStatus)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		api.fund_info(at, index).map_err(runtime_error_into_rpc_err)
+	}
+}
+
+/// Error code for runtime errors.
+const RUNTIME_ERROR: i32 = 1;
+
+/// Converts a runtime trap into an RPC error.
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		RUNTIME_ERROR,
+		"Runtime error",
+		Some(format!("{:?}", err)),
+	))
+	.into()
+}
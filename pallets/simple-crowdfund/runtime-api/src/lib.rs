@@ -0,0 +1,25 @@
+//! Runtime API definition for the simple-crowdfund pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+pub use simple_crowdfund::{FundIndex, FundInfo, FundLimits, FundStatus};
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait SimpleCrowdfundApi<AccountId, Balance, BlockNumber> where
+		AccountId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Up to `limit` contributors to the fund at `index` with their contributions, starting
+		/// after `start_key`.
+		fn contributions(
+			index: FundIndex,
+			start_key: Option<AccountId>,
+			limit: u32,
+		) -> Vec<(AccountId, Balance)>;
+		/// The fund at `index` with its current status, if it exists.
+		fn fund_info(index: FundIndex) -> Option<(FundInfo<AccountId, Balance, BlockNumber>, FundStatus)>;
+	}
+}
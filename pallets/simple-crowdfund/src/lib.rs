@@ -1,18 +1,37 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use codec::Encode;
-use frame_support::{storage::child, traits::Currency, PalletId};
+use codec::{Decode, Encode};
+use frame_support::{
+	sp_io::default_child_storage,
+	sp_std::vec::Vec,
+	storage::{child, ChildTriePrefixIterator},
+	traits::{Currency, ExistenceRequirement, Get, WithdrawReasons},
+	weights::Weight,
+	BoundedVec, Identity, PalletId,
+};
 use frame_system::pallet_prelude::BlockNumberFor;
 use sp_core::Hasher;
-use sp_runtime::traits::AccountIdConversion;
+use sp_runtime::{
+	helpers_128bit::multiply_rational,
+	traits::{AccountIdConversion, Zero},
+	DispatchError, DispatchResult, Rounding, SaturatedConversion, Saturating,
+};
 
 pub type FundIndex = u32;
 type AccountIdOf<T> = <T as frame_system::Config>::AccountId;
 type BalanceOf<T> = <<T as Config>::Currency as Currency<AccountIdOf<T>>>::Balance;
 type FundInfoOf<T> = FundInfo<AccountIdOf<T>, BalanceOf<T>, BlockNumberFor<T>>;
+type MilestoneProgressOf<T> = MilestoneProgress<BalanceOf<T>, BlockNumberFor<T>>;
+/// A milestone schedule: the amount and description of each milestone, in order.
+pub type MilestonesOf<T> = BoundedVec<
+	(BalanceOf<T>, BoundedVec<u8, <T as Config>::MaxDescriptionLength>),
+	<T as Config>::MaxMilestones,
+>;
 
 pub use pallet::*;
 
+pub mod migrations;
+
 #[cfg(test)]
 mod mock;
 
@@ -23,15 +42,21 @@ const PALLET_ID: PalletId = PalletId(*b"ex/cfund");
 
 #[frame_support::pallet(dev_mode)]
 pub mod pallet {
-	use crate::{AccountIdOf, BalanceOf, FundIndex, FundInfoOf};
+	use crate::{AccountIdOf, BalanceOf, FundIndex, FundInfoOf, MilestoneProgressOf, MilestonesOf};
+	use account_set::AccountSet;
 	use frame_support::{
 		pallet_prelude::*,
+		sp_std::vec::Vec,
 		traits::{Currency, ExistenceRequirement, ReservableCurrency, WithdrawReasons},
 	};
 	use frame_system::pallet_prelude::*;
 	use sp_runtime::{traits::Zero, Saturating};
 
+	/// The current storage version.
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
@@ -50,10 +75,30 @@ pub mod pallet {
 		/// The period of time (in blocks) after an unsuccessful crowdfund ending during which
 		/// contributors are able to withdraw their funds. After this period, their funds are lost.
 		type RetirementPeriod: Get<BlockNumberFor<Self>>;
+
+		/// The maximum number of milestones in a fund's schedule
+		type MaxMilestones: Get<u32>;
+
+		/// The maximum length of a milestone's description
+		type MaxDescriptionLength: Get<u32>;
+
+		/// The period of time (in blocks) during which contributors can vote on a claimed
+		/// milestone
+		type MilestoneVotingPeriod: Get<BlockNumberFor<Self>>;
+
+		/// The accounts allowed to contribute to funds that are restricted to an allow-list
+		type AllowList: AccountSet<AccountId = Self::AccountId>;
+
+		/// The most votes and contributions of removed funds that `on_idle` clears in a block
+		#[pallet::constant]
+		type MaxRemovalsPerBlock: Get<u32>;
 	}
 
 	#[derive(Encode, Decode, Clone, TypeInfo, PartialEq, RuntimeDebug)]
+	#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 	pub struct FundInfo<AccountId, Balance, BlockNumber> {
+		/// The account that created the fund and placed the deposit
+		pub creator: AccountId,
 		/// The account that will receive the funds if the campaign is successful
 		pub beneficiary: AccountId,
 		/// The amount of deposit placed
@@ -62,8 +107,75 @@ pub mod pallet {
 		pub raised: Balance,
 		/// Block number after which funding must have succeeded
 		pub end: BlockNumber,
-		/// Upper bound on `raised`
+		/// The amount that must be raised for the fund to succeed
 		pub goal: Balance,
+		/// Limits on who may contribute and how much
+		pub limits: FundLimits<Balance>,
+	}
+
+	/// Limits on the contributions to a fund.
+	#[derive(Encode, Decode, Clone, Default, TypeInfo, PartialEq, RuntimeDebug)]
+	#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+	pub struct FundLimits<Balance> {
+		/// Upper bound on `raised`. The fund closes early once it is reached, or once the room
+		/// left under it is less than `MinContribution`.
+		pub cap: Option<Balance>,
+		/// The most that a single account may contribute in total
+		pub max_contribution: Option<Balance>,
+		/// Only accounts in the `AllowList` may contribute
+		pub allow_list: bool,
+	}
+
+	/// Where a fund is in its lifecycle.
+	#[derive(Encode, Decode, Clone, Copy, TypeInfo, PartialEq, Eq, RuntimeDebug)]
+	#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+	pub enum FundStatus {
+		/// Contributions are still accepted
+		Active,
+		/// The fund ended having reached its goal, and can be dispensed or is paying out
+		/// milestones
+		Succeeded,
+		/// The fund ended short of its goal or had a milestone rejected, and contributors can
+		/// withdraw
+		Failed,
+		/// The fund failed and its retirement period is over, so it can be dissolved
+		Retired,
+	}
+
+	/// How far a fund has got through its milestone schedule.
+	#[derive(Encode, Decode, Clone, TypeInfo, PartialEq, RuntimeDebug)]
+	pub struct MilestoneProgress<Balance, BlockNumber> {
+		/// The milestone to be claimed or voted on next
+		pub next: u32,
+		pub status: MilestoneStatus<Balance, BlockNumber>,
+	}
+
+	impl<Balance, BlockNumber> MilestoneProgress<Balance, BlockNumber> {
+		/// Whether the beneficiary has claimed a milestone, which they can only do once the fund
+		/// has succeeded.
+		pub fn started(&self) -> bool {
+			self.next > 0 || !matches!(self.status, MilestoneStatus::Pending)
+		}
+	}
+
+	#[derive(Encode, Decode, Clone, TypeInfo, PartialEq, RuntimeDebug)]
+	pub enum MilestoneStatus<Balance, BlockNumber> {
+		/// Waiting for the beneficiary to claim the next milestone
+		Pending,
+		/// Contributors are voting on the next milestone until `end`
+		Voting { end: BlockNumber, ayes: Balance, nays: Balance },
+		/// The next milestone was rejected at block `at`. Contributors share the `pot` that was
+		/// left in proportion to their part of the `total` contributed.
+		Rejected { at: BlockNumber, pot: Balance, total: Balance },
+	}
+
+	/// What is left to clear of a removed fund.
+	#[derive(Encode, Decode, Clone, TypeInfo, PartialEq, RuntimeDebug)]
+	pub enum Leftover {
+		/// Milestone votes, resuming after the raw key in the cursor if there is one
+		Votes(Option<Vec<u8>>),
+		/// Contributions, resuming after the raw key in the cursor if there is one
+		Contributions(Option<Vec<u8>>),
 	}
 
 	#[pallet::storage]
@@ -74,6 +186,35 @@ pub mod pallet {
 	#[pallet::getter(fn fund_count)]
 	pub type FundCount<T> = StorageValue<_, FundIndex, ValueQuery>;
 
+	/// The milestone schedule of funds that pay out in stages.
+	#[pallet::storage]
+	#[pallet::getter(fn milestones)]
+	pub type Milestones<T> =
+		StorageMap<_, Blake2_128Concat, FundIndex, MilestonesOf<T>, OptionQuery>;
+
+	/// How far each fund with a milestone schedule has got through it.
+	#[pallet::storage]
+	#[pallet::getter(fn milestone_progress)]
+	pub type Progress<T> =
+		StorageMap<_, Blake2_128Concat, FundIndex, MilestoneProgressOf<T>, OptionQuery>;
+
+	/// The last milestone of a fund that each contributor voted on.
+	#[pallet::storage]
+	pub type MilestoneVotes<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		FundIndex,
+		Blake2_128Concat,
+		T::AccountId,
+		u32,
+		OptionQuery,
+	>;
+
+	/// Funds that have been removed but whose milestone votes and contributions are still being
+	/// cleared by `on_idle`.
+	#[pallet::storage]
+	pub type Leftovers<T> = StorageMap<_, Blake2_128Concat, FundIndex, Leftover, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -82,6 +223,27 @@ pub mod pallet {
 		Withdrew(T::AccountId, FundIndex, BalanceOf<T>, BlockNumberFor<T>),
 		Dissolved(FundIndex, BlockNumberFor<T>, T::AccountId),
 		Dispensed(FundIndex, BlockNumberFor<T>, T::AccountId),
+		/// Contributors of a failed fund were refunded. [index, refunded, remaining]
+		///
+		/// `remaining` is true if some contributors are still to be refunded.
+		Refunded(FundIndex, u32, bool),
+		/// The beneficiary claimed a milestone, opening a vote on it. [index, milestone, end]
+		MilestoneClaimed(FundIndex, u32, BlockNumberFor<T>),
+		/// A contributor voted on a milestone with the weight of their contribution. [who, index,
+		/// milestone, approve, weight]
+		MilestoneVoted(T::AccountId, FundIndex, u32, bool, BalanceOf<T>),
+		/// A milestone was approved and paid to the beneficiary. [index, milestone, amount]
+		MilestoneApproved(FundIndex, u32, BalanceOf<T>),
+		/// A milestone was rejected, and what is left of the fund goes back to the contributors.
+		/// [index, milestone, remaining]
+		MilestoneRejected(FundIndex, u32, BalanceOf<T>),
+		/// A fund reached its cap, or has too little room left under it for another
+		/// contribution, and closed before its end. [index, block]
+		ClosedEarly(FundIndex, BlockNumberFor<T>),
+		/// A fund was cancelled by its creator before anyone contributed. [index, block]
+		Cancelled(FundIndex, BlockNumberFor<T>),
+		/// The milestone votes and contributions of a removed fund have all been cleared. [index]
+		Cleared(FundIndex),
 	}
 
 	#[pallet::error]
@@ -102,11 +264,78 @@ pub mod pallet {
 		FundNotRetired,
 		/// Cannot dispense funds from an unsuccessful fund
 		UnsuccessfulFund,
+		/// Cannot refund contributions to a fund that reached its goal
+		SuccessfulFund,
+		/// The milestone amounts must add up to the goal
+		MilestonesDontMatchGoal,
+		/// The fund has no milestone schedule
+		NoMilestones,
+		/// Funds with a milestone schedule are paid out one milestone at a time
+		HasMilestones,
+		/// Only the beneficiary can claim milestones
+		NotBeneficiary,
+		/// A milestone is already being voted on
+		VoteInProgress,
+		/// No milestone is being voted on
+		NoVoteInProgress,
+		/// The voting period for the milestone has ended
+		VotingPeriodOver,
+		/// The voting period for the milestone has not ended yet
+		VotingPeriodActive,
+		/// You have already voted on this milestone
+		AlreadyVoted,
+		/// A milestone of the fund was rejected, so no more can be claimed
+		MilestoneRejected,
+		/// Cannot dissolve a fund while it is paying out milestones
+		MilestonesInProgress,
+		/// The cap must be at least the goal
+		CapBelowGoal,
+		/// The maximum contribution must be at least the minimum contribution
+		MaxContributionTooSmall,
+		/// The contribution would take the fund past its cap
+		CapExceeded,
+		/// The contribution would take you past the maximum contribution of the fund
+		ContributionTooLarge,
+		/// Only accounts in the allow-list may contribute to this fund
+		NotAllowed,
+		/// Only the creator of a fund can cancel it
+		NotCreator,
+		/// Cannot cancel a fund that has been contributed to
+		HasContributions,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		/// Spend leftover block weight on clearing the storage of removed funds.
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let lookup = T::DbWeight::get().reads(1);
+			if remaining_weight.any_lt(lookup) {
+				return Weight::zero()
+			}
+			let Some(index) = <Leftovers<T>>::iter_keys().next() else { return lookup };
+
+			let limit = Self::removals_within(remaining_weight.saturating_sub(lookup));
+			if limit == 0 {
+				return lookup
+			}
+			let removed = Self::clear_leftovers_step(index, limit);
+			lookup.saturating_add(Self::clear_weight(removed))
+		}
+
+		fn integrity_test() {
+			// Removed funds could never be cleared, since every step would remove nothing.
+			assert!(T::MaxRemovalsPerBlock::get() > 0, "`MaxRemovalsPerBlock` must not be zero");
+		}
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		/// Create a new fund
+		///
+		/// With a schedule of `milestones`, a successful fund is paid out one milestone at a time,
+		/// each approved by the contributors, instead of being dispensed at once. `limits` can cap
+		/// the fund, bound each account's contribution and restrict contributors to the
+		/// `AllowList`.
 		#[pallet::call_index(0)]
 		#[pallet::weight(10_000)]
 		pub fn create(
@@ -114,10 +343,29 @@ pub mod pallet {
 			beneficiary: AccountIdOf<T>,
 			goal: BalanceOf<T>,
 			end: BlockNumberFor<T>,
+			milestones: Option<MilestonesOf<T>>,
+			limits: FundLimits<BalanceOf<T>>,
 		) -> DispatchResult {
 			let creator = ensure_signed(origin)?;
 			let block_number = frame_system::Pallet::<T>::block_number();
 			ensure!(end > block_number, Error::<T>::EndTooEarly);
+			if let Some(cap) = limits.cap {
+				ensure!(cap >= goal, Error::<T>::CapBelowGoal);
+			}
+			if let Some(max_contribution) = limits.max_contribution {
+				ensure!(
+					max_contribution >= T::MinContribution::get(),
+					Error::<T>::MaxContributionTooSmall
+				);
+			}
+
+			let milestones = milestones.filter(|milestones| !milestones.is_empty());
+			if let Some(milestones) = &milestones {
+				let total = milestones.iter().fold(BalanceOf::<T>::zero(), |total, (amount, _)| {
+					total.saturating_add(*amount)
+				});
+				ensure!(total == goal, Error::<T>::MilestonesDontMatchGoal);
+			}
 
 			let deposit = T::SubmissionDeposit::get();
 			let imb = T::Currency::withdraw(
@@ -137,8 +385,15 @@ pub mod pallet {
 
 			<Funds<T>>::insert(
 				index,
-				FundInfo { beneficiary, deposit, raised: Zero::zero(), end, goal },
+				FundInfo { creator, beneficiary, deposit, raised: Zero::zero(), end, goal, limits },
 			);
+			if let Some(milestones) = milestones {
+				<Milestones<T>>::insert(index, milestones);
+				<Progress<T>>::insert(
+					index,
+					MilestoneProgress { next: 0, status: MilestoneStatus::Pending },
+				);
+			}
 
 			Self::deposit_event(Event::Created(index, block_number));
 			Ok(())
@@ -157,6 +412,20 @@ pub mod pallet {
 			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
 			let block_number = frame_system::Pallet::<T>::block_number();
 			ensure!(fund.end > block_number, Error::<T>::ContributionPeriodOver);
+			ensure!(
+				!fund.limits.allow_list || T::AllowList::contains(&who),
+				Error::<T>::NotAllowed
+			);
+
+			let balance = Self::contribution_get(index, &who);
+			let balance = balance.saturating_add(value);
+			if let Some(max_contribution) = fund.limits.max_contribution {
+				ensure!(balance <= max_contribution, Error::<T>::ContributionTooLarge);
+			}
+			let raised = fund.raised.saturating_add(value);
+			if let Some(cap) = fund.limits.cap {
+				ensure!(raised <= cap, Error::<T>::CapExceeded);
+			}
 
 			// Add contribution to the fund
 			T::Currency::transfer(
@@ -165,14 +434,24 @@ pub mod pallet {
 				value,
 				ExistenceRequirement::AllowDeath,
 			)?;
-			fund.raised += value;
+			fund.raised = raised;
+			// Reaching the cap ends the fund, so that it can be paid out straight away. So does
+			// getting so close to it that no contribution would fit.
+			let capped = fund
+				.limits
+				.cap
+				.map_or(false, |cap| cap.saturating_sub(raised) < T::MinContribution::get());
+			if capped {
+				fund.end = block_number;
+			}
 			Funds::<T>::insert(index, &fund);
 
-			let balance = Self::contribution_get(index, &who);
-			let balance = balance.saturating_add(value);
 			Self::contribution_put(index, &who, &balance);
 
 			Self::deposit_event(Event::Contributed(who, index, balance, block_number));
+			if capped {
+				Self::deposit_event(Event::ClosedEarly(index, block_number));
+			}
 
 			Ok(())
 		}
@@ -185,27 +464,16 @@ pub mod pallet {
 
 			let mut crowdfund = Self::funds(&index).ok_or(Error::<T>::InvalidIndex)?;
 			let block_number = frame_system::Pallet::<T>::block_number();
-			ensure!(crowdfund.end < block_number, Error::<T>::FundStillActive);
+			let terms = Self::refund_terms(index, &crowdfund, block_number)?;
 
 			let balance = Self::contribution_get(index, &caller);
 			ensure!(balance > Zero::zero(), Error::<T>::NoContribution);
 
-			T::Currency::resolve_creating(
-				&caller,
-				T::Currency::withdraw(
-					&Self::fund_account_id(index),
-					balance,
-					WithdrawReasons::TRANSFER,
-					ExistenceRequirement::AllowDeath,
-				)?,
-			);
-
-			// Update storage
-			Self::contribution_kill(index, &caller);
-			crowdfund.raised = crowdfund.raised.saturating_add(balance);
+			let amount = Self::refund_amount(balance, terms);
+			Self::refund(index, &mut crowdfund, &caller, amount)?;
 			<Funds<T>>::insert(index, &crowdfund);
 
-			Self::deposit_event(Event::Withdrew(caller, index, balance, block_number));
+			Self::deposit_event(Event::Withdrew(caller, index, amount, block_number));
 			Ok(())
 		}
 
@@ -213,7 +481,8 @@ pub mod pallet {
 		/// Anyone can call this function, and they are incentivized to do so because
 		/// they inherit the deposit.
 		#[pallet::call_index(3)]
-		#[pallet::weight(10_000)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(4, 6)))]
 		pub fn dissolve(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
 			let reporter = ensure_signed(origin)?;
 
@@ -222,7 +491,7 @@ pub mod pallet {
 			// Check that enough time has passed to remove from storage
 			let block_number = frame_system::Pallet::<T>::block_number();
 			ensure!(
-				block_number >= fund.end + T::RetirementPeriod::get(),
+				block_number >= Self::retirement_start(index, &fund)? + T::RetirementPeriod::get(),
 				Error::<T>::FundNotRetired
 			);
 
@@ -239,11 +508,7 @@ pub mod pallet {
 				)?,
 			);
 
-			// Remove the fund info from storage
-			<Funds<T>>::remove(index);
-			// Remove all the contributor info from storage in a single write.
-			// This is possible thanks to the use of a child tree.
-			Self::crowdfund_kill(index);
+			Self::remove_fund(index);
 
 			Self::deposit_event(Event::Dissolved(index, block_number, reporter));
 
@@ -253,7 +518,8 @@ pub mod pallet {
 		/// The beneficiary receives the contributed funds and the caller receives
 		/// the deposit as a reward to incentivize clearing settled crowdfunds out of storage.
 		#[pallet::call_index(4)]
-		#[pallet::weight(10_000)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(5, 7)))]
 		pub fn dispense(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
 			let caller = ensure_signed(origin)?;
 
@@ -266,6 +532,7 @@ pub mod pallet {
 
 			// Check that the fund was actually successful
 			ensure!(fund.raised >= fund.goal, Error::<T>::UnsuccessfulFund);
+			ensure!(!<Milestones<T>>::contains_key(index), Error::<T>::HasMilestones);
 
 			let account = Self::fund_account_id(index);
 
@@ -291,16 +558,207 @@ pub mod pallet {
 				)?,
 			);
 
-			// Remove the fund info from storage
-			<Funds<T>>::remove(index);
-			// Remove all the contributor info from storage in a single write.
-			// This is possible thanks to the use of a child tree.
-			Self::crowdfund_kill(index);
+			Self::remove_fund(index);
 
 			Self::deposit_event(Event::Dispensed(index, now, caller));
 
 			Ok(())
 		}
+
+		/// Refund up to `limit` contributors of a fund that missed its goal.
+		///
+		/// Anyone can call this function, as many times as it takes to refund everybody before
+		/// the fund is dissolved. Each contributor is refunded as if they had called `withdraw`.
+		#[pallet::call_index(5)]
+		#[pallet::weight(Pallet::<T>::refund_all_weight(*limit))]
+		pub fn refund_all(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			limit: u32,
+		) -> DispatchResultWithPostInfo {
+			let _caller = ensure_signed(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			let block_number = frame_system::Pallet::<T>::block_number();
+			let terms = Self::refund_terms(index, &fund, block_number)?;
+
+			// Refunded contributions are removed from the trie, so each page starts from the
+			// beginning. One more than the limit is read to tell whether any remain.
+			let mut contributions = Self::contributions(index)
+				.take((limit as usize).saturating_add(1))
+				.collect::<Vec<_>>();
+			let remaining = contributions.len() > limit as usize;
+			contributions.truncate(limit as usize);
+
+			let refunded = contributions.len() as u32;
+			for (who, balance) in contributions {
+				let amount = Self::refund_amount(balance, terms);
+				Self::refund(index, &mut fund, &who, amount)?;
+				Self::deposit_event(Event::Withdrew(who, index, amount, block_number));
+			}
+			<Funds<T>>::insert(index, &fund);
+
+			Self::deposit_event(Event::Refunded(index, refunded, remaining));
+			Ok(Some(Self::refund_all_weight(refunded)).into())
+		}
+
+		/// Claim the next milestone of a successful fund, opening a vote on it among the
+		/// contributors.
+		#[pallet::call_index(6)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(2, 1)))]
+		pub fn claim_milestone(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
+			let caller = ensure_signed(origin)?;
+
+			let fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			ensure!(caller == fund.beneficiary, Error::<T>::NotBeneficiary);
+			let mut progress = Self::milestone_progress(index).ok_or(Error::<T>::NoMilestones)?;
+
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(now >= fund.end, Error::<T>::FundStillActive);
+			// Payouts take `raised` below the goal, but only a successful fund gets that far
+			ensure!(progress.started() || fund.raised >= fund.goal, Error::<T>::UnsuccessfulFund);
+			match progress.status {
+				MilestoneStatus::Pending => {},
+				MilestoneStatus::Voting { .. } => return Err(Error::<T>::VoteInProgress.into()),
+				MilestoneStatus::Rejected { .. } =>
+					return Err(Error::<T>::MilestoneRejected.into()),
+			}
+
+			let end = now.saturating_add(T::MilestoneVotingPeriod::get());
+			progress.status =
+				MilestoneStatus::Voting { end, ayes: Zero::zero(), nays: Zero::zero() };
+			<Progress<T>>::insert(index, &progress);
+
+			Self::deposit_event(Event::MilestoneClaimed(index, progress.next, end));
+			Ok(())
+		}
+
+		/// Vote on the milestone being claimed from a fund, with the weight of your contribution
+		/// to it.
+		#[pallet::call_index(7)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(4, 2)))]
+		pub fn vote_milestone(
+			origin: OriginFor<T>,
+			index: FundIndex,
+			approve: bool,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(<Funds<T>>::contains_key(index), Error::<T>::InvalidIndex);
+			let mut progress = Self::milestone_progress(index).ok_or(Error::<T>::NoMilestones)?;
+			let milestone = progress.next;
+			let MilestoneStatus::Voting { end, ayes, nays } = &mut progress.status else {
+				return Err(Error::<T>::NoVoteInProgress.into())
+			};
+
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(now < *end, Error::<T>::VotingPeriodOver);
+			ensure!(
+				<MilestoneVotes<T>>::get(index, &who) != Some(milestone),
+				Error::<T>::AlreadyVoted
+			);
+
+			let weight = Self::contribution_get(index, &who);
+			ensure!(weight > Zero::zero(), Error::<T>::NoContribution);
+			if approve {
+				*ayes = ayes.saturating_add(weight);
+			} else {
+				*nays = nays.saturating_add(weight);
+			}
+			<MilestoneVotes<T>>::insert(index, &who, milestone);
+			<Progress<T>>::insert(index, &progress);
+
+			Self::deposit_event(Event::MilestoneVoted(who, index, milestone, approve, weight));
+			Ok(())
+		}
+
+		/// Close the vote on a fund's milestone once its voting period is over.
+		///
+		/// Anyone can call this function. A milestone passes unless more contributions voted
+		/// against it than for it, so the beneficiary isn't held up by contributors who don't
+		/// vote. An approved milestone is paid to the beneficiary; the last one also pays out
+		/// anything raised beyond the goal, and the caller receives the deposit as for `dispense`.
+		/// A rejected milestone ends the schedule, and contributors can withdraw their share of
+		/// what is left.
+		#[pallet::call_index(8)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(7, 8)))]
+		pub fn conclude_milestone(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
+			let caller = ensure_signed(origin)?;
+
+			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			let milestones = Self::milestones(index).ok_or(Error::<T>::NoMilestones)?;
+			let mut progress = Self::milestone_progress(index).ok_or(Error::<T>::NoMilestones)?;
+			let MilestoneStatus::Voting { end, ayes, nays } = progress.status else {
+				return Err(Error::<T>::NoVoteInProgress.into())
+			};
+
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(now >= end, Error::<T>::VotingPeriodActive);
+			let milestone = progress.next;
+
+			if ayes < nays {
+				// Nothing has been refunded yet, so the contributions add up to what is left plus
+				// what was paid for earlier milestones.
+				let paid = milestones
+					.iter()
+					.take(milestone as usize)
+					.fold(BalanceOf::<T>::zero(), |paid, (amount, _)| paid.saturating_add(*amount));
+				let total = fund.raised.saturating_add(paid);
+				progress.status = MilestoneStatus::Rejected { at: now, pot: fund.raised, total };
+				<Progress<T>>::insert(index, &progress);
+
+				Self::deposit_event(Event::MilestoneRejected(index, milestone, fund.raised));
+				return Ok(())
+			}
+
+			if milestone as usize + 1 < milestones.len() {
+				let amount = milestones[milestone as usize].0.min(fund.raised);
+				Self::pay_from_fund(index, &fund.beneficiary, amount)?;
+				fund.raised = fund.raised.saturating_sub(amount);
+				<Funds<T>>::insert(index, &fund);
+
+				progress.next = milestone + 1;
+				progress.status = MilestoneStatus::Pending;
+				<Progress<T>>::insert(index, &progress);
+
+				Self::deposit_event(Event::MilestoneApproved(index, milestone, amount));
+				return Ok(())
+			}
+
+			// The last milestone: the beneficiary collects the rest and the caller the deposit
+			Self::pay_from_fund(index, &fund.beneficiary, fund.raised)?;
+			Self::pay_from_fund(index, &caller, fund.deposit)?;
+			Self::remove_fund(index);
+
+			Self::deposit_event(Event::MilestoneApproved(index, milestone, fund.raised));
+			Self::deposit_event(Event::Dispensed(index, now, caller));
+			Ok(())
+		}
+
+		/// Cancel a fund that nobody has contributed to, returning the deposit to its creator.
+		#[pallet::call_index(9)]
+		#[pallet::weight(Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(4, 6)))]
+		pub fn cancel(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
+			let caller = ensure_signed(origin)?;
+
+			let fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			ensure!(caller == fund.creator, Error::<T>::NotCreator);
+			ensure!(
+				fund.raised.is_zero() && Self::contributions(index).next().is_none(),
+				Error::<T>::HasContributions
+			);
+
+			Self::pay_from_fund(index, &caller, fund.deposit)?;
+			Self::remove_fund(index);
+
+			let block_number = frame_system::Pallet::<T>::block_number();
+			Self::deposit_event(Event::Cancelled(index, block_number));
+			Ok(())
+		}
 	}
 }
 
@@ -312,6 +770,243 @@ impl<T: Config> Pallet<T> {
 	fn fund_account_id(index: FundIndex) -> T::AccountId {
 		PALLET_ID.into_sub_account_truncating(index)
 	}
+	/// The status of the fund `fund` at `index` at block `now`.
+	pub fn fund_status(
+		index: FundIndex,
+		fund: &FundInfoOf<T>,
+		now: BlockNumberFor<T>,
+	) -> FundStatus {
+		if fund.end > now {
+			return FundStatus::Active
+		}
+		let failed_at = match Self::milestone_progress(index) {
+			Some(MilestoneProgress { status: MilestoneStatus::Rejected { at, .. }, .. }) => at,
+			Some(progress) if progress.started() => return FundStatus::Succeeded,
+			_ if fund.raised >= fund.goal => return FundStatus::Succeeded,
+			_ => fund.end,
+		};
+		if now >= failed_at.saturating_add(T::RetirementPeriod::get()) {
+			FundStatus::Retired
+		} else {
+			FundStatus::Failed
+		}
+	}
+
+	/// The fund at `index` with its current status, if it exists.
+	pub fn fund_info(index: FundIndex) -> Option<(FundInfoOf<T>, FundStatus)> {
+		let fund = Self::funds(index)?;
+		let status = Self::fund_status(index, &fund, frame_system::Pallet::<T>::block_number());
+		Some((fund, status))
+	}
+
+	/// Up to `limit` contributions to the fund at `index`, continuing after the contribution of
+	/// `start`.
+	///
+	/// Contributions are listed in the order of the child trie, so passing the last contributor
+	/// of a page returns the next one. `None` starts from the beginning.
+	pub fn contributions_paged(
+		index: FundIndex,
+		start: Option<&T::AccountId>,
+		limit: u32,
+	) -> Vec<(T::AccountId, BalanceOf<T>)> {
+		let id = Self::id_from_index(index);
+		let mut key = start.map(Encode::encode).unwrap_or_default();
+		let mut page = Vec::new();
+		while (page.len() as u32) < limit {
+			let Some(next) = default_child_storage::next_key(id.storage_key(), &key) else { break };
+			if let (Ok(who), Some(balance)) =
+				(T::AccountId::decode(&mut &next[..]), child::get(&id, &next))
+			{
+				page.push((who, balance));
+			}
+			key = next;
+		}
+		page
+	}
+
+	/// Check that contributions to the fund `fund` at `index` may be refunded, and on which
+	/// terms.
+	///
+	/// Contributions are refunded in full once the fund has ended without reaching its goal.
+	/// After a milestone is rejected, the returned `(pot, total)` means each contributor gets the
+	/// share of `pot` that their contribution is of `total`.
+	fn refund_terms(
+		index: FundIndex,
+		fund: &FundInfoOf<T>,
+		now: BlockNumberFor<T>,
+	) -> Result<Option<(BalanceOf<T>, BalanceOf<T>)>, DispatchError> {
+		let progress = Self::milestone_progress(index);
+		if let Some(MilestoneProgress {
+			status: MilestoneStatus::Rejected { pot, total, .. },
+			..
+		}) = progress
+		{
+			return Ok(Some((pot, total)))
+		}
+
+		frame_support::ensure!(fund.end < now, Error::<T>::FundStillActive);
+		let started = progress.map_or(false, |progress| progress.started());
+		frame_support::ensure!(fund.raised < fund.goal && !started, Error::<T>::SuccessfulFund);
+		Ok(None)
+	}
+
+	/// How much of `contribution` is refunded under `terms`, as returned by `refund_terms`.
+	fn refund_amount(
+		contribution: BalanceOf<T>,
+		terms: Option<(BalanceOf<T>, BalanceOf<T>)>,
+	) -> BalanceOf<T> {
+		match terms {
+			// Rounding down keeps the shares from adding up to more than the pot. The share is
+			// exact, however small the contribution.
+			Some((pot, total)) => multiply_rational(
+				contribution.saturated_into(),
+				pot.saturated_into(),
+				total.saturated_into(),
+				Rounding::Down,
+			)
+			.map_or_else(Zero::zero, |share| share.saturated_into()),
+			None => contribution,
+		}
+	}
+
+	/// The block from which the retirement period of the fund `fund` at `index` runs.
+	///
+	/// Fails while the fund is paying out milestones, so that it can't be dissolved from under
+	/// its beneficiary.
+	fn retirement_start(
+		index: FundIndex,
+		fund: &FundInfoOf<T>,
+	) -> Result<BlockNumberFor<T>, DispatchError> {
+		match Self::milestone_progress(index) {
+			Some(MilestoneProgress { status: MilestoneStatus::Rejected { at, .. }, .. }) => Ok(at),
+			Some(progress) if progress.started() => Err(Error::<T>::MilestonesInProgress.into()),
+			_ => Ok(fund.end),
+		}
+	}
+
+	/// Pay `amount` back to `who` out of the fund pot, and remove their contribution from the
+	/// fund.
+	fn refund(
+		index: FundIndex,
+		fund: &mut FundInfoOf<T>,
+		who: &T::AccountId,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		Self::pay_from_fund(index, who, amount)?;
+
+		Self::contribution_kill(index, who);
+		fund.raised = fund.raised.saturating_sub(amount);
+		Ok(())
+	}
+
+	/// Pay `amount` to `who` out of the pot of the fund at `index`.
+	fn pay_from_fund(index: FundIndex, who: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+		T::Currency::resolve_creating(
+			who,
+			T::Currency::withdraw(
+				&Self::fund_account_id(index),
+				amount,
+				WithdrawReasons::TRANSFER,
+				ExistenceRequirement::AllowDeath,
+			)?,
+		);
+		Ok(())
+	}
+
+	/// Remove the fund at `index` from storage.
+	///
+	/// Its milestone votes and contributions may be many, so they are left for `on_idle` to
+	/// clear over as many blocks as it takes.
+	fn remove_fund(index: FundIndex) {
+		<Funds<T>>::remove(index);
+		<Milestones<T>>::remove(index);
+		<Progress<T>>::remove(index);
+		<Leftovers<T>>::insert(index, Leftover::Votes(None));
+	}
+
+	/// Clear up to `limit` milestone votes and contributions of the removed fund at `index`,
+	/// resuming from its stored cursor.
+	///
+	/// Returns the number of keys visited. Once everything is cleared the fund is dropped from
+	/// `Leftovers` and `Cleared` is emitted.
+	fn clear_leftovers_step(index: FundIndex, limit: u32) -> u32 {
+		// Nothing would be removed, so there is no telling whether the fund is cleared yet.
+		if limit == 0 {
+			return 0
+		}
+		let Some(mut leftover) = <Leftovers<T>>::get(index) else { return 0 };
+
+		let mut removed = 0u32;
+		while removed < limit {
+			let remaining = limit - removed;
+			let (results, next) = match leftover {
+				Leftover::Votes(cursor) => {
+					let results =
+						<MilestoneVotes<T>>::clear_prefix(index, remaining, cursor.as_deref());
+					let next = match &results.maybe_cursor {
+						Some(cursor) => Leftover::Votes(Some(cursor.clone())),
+						None => Leftover::Contributions(None),
+					};
+					(results, Some(next))
+				},
+				Leftover::Contributions(cursor) => {
+					// The child trie may not resume from the cursor, but keys cleared in earlier
+					// blocks are gone, and `on_idle` takes a single step per block.
+					let id = Self::id_from_index(index);
+					let results = child::clear_storage(&id, Some(remaining), cursor.as_deref());
+					let next =
+						results.maybe_cursor.clone().map(|c| Leftover::Contributions(Some(c)));
+					(results, next)
+				},
+			};
+			removed = removed.saturating_add(results.loops);
+
+			match next {
+				Some(next) => leftover = next,
+				None => {
+					<Leftovers<T>>::remove(index);
+					Self::deposit_event(Event::Cleared(index));
+					return removed
+				},
+			}
+		}
+
+		<Leftovers<T>>::insert(index, leftover);
+		removed
+	}
+
+	/// Weight of a clearing step that visited `removed` keys.
+	///
+	/// Each key costs one read to find it and one write to drop it; the leftover cursor is read
+	/// and written once.
+	pub fn clear_weight(removed: u32) -> Weight {
+		let removed = u64::from(removed);
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(1 + removed, 1 + removed))
+	}
+
+	/// How many keys can be cleared without exceeding `budget`.
+	fn removals_within(budget: Weight) -> u32 {
+		let base = Self::clear_weight(0);
+		if budget.any_lt(base) {
+			return 0
+		}
+		let per_removal = Self::clear_weight(1).saturating_sub(base);
+		budget
+			.saturating_sub(base)
+			.checked_div_per_component(&per_removal)
+			.unwrap_or(u64::MAX)
+			.min(T::MaxRemovalsPerBlock::get().into()) as u32
+	}
+
+	/// Weight of `refund_all` refunding `n` contributors: the fund is read and written once, and
+	/// each refund reads and writes both accounts and removes a contribution.
+	fn refund_all_weight(n: u32) -> Weight {
+		let n = u64::from(n);
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads_writes(2 + 3 * n, 1 + 3 * n))
+	}
+
 	/// Find the ID associated with the fund
 	///
 	/// Each fund stores information about its contributors and their contributions in a child trie
@@ -336,19 +1031,18 @@ impl<T: Config> Pallet<T> {
 		who.using_encoded(|sl| child::get_or_default(&id, sl))
 	}
 
-	/// Remove a contribution from an associated child trie.
-	fn contribution_kill(index: FundIndex, who: &T::AccountId) {
+	/// Iterate over the contributions in the associated child trie.
+	///
+	/// Contributions are keyed by the raw encoding of the contributor, so they are listed in
+	/// that order.
+	fn contributions(index: FundIndex) -> ChildTriePrefixIterator<(T::AccountId, BalanceOf<T>)> {
 		let id = Self::id_from_index(index);
-		who.using_encoded(|sl| child::kill(&id, sl));
+		ChildTriePrefixIterator::with_prefix_over_key::<Identity>(&id, &[])
 	}
 
-	/// Remove the entire record of contributions in the associated child trie in a single
-	/// storage write.
-	fn crowdfund_kill(index: FundIndex) {
+	/// Remove a contribution from an associated child trie.
+	fn contribution_kill(index: FundIndex, who: &T::AccountId) {
 		let id = Self::id_from_index(index);
-		// The None here means we aren't setting a limit to how many keys to delete.
-		// Limiting can be useful, but is beyond the scope of this recipe. For more info, see
-		// https://crates.parity.io/frame_support/storage/child/fn.kill_storage.html
-		let _ = child::clear_storage(&id, None, None);
+		who.using_encoded(|sl| child::kill(&id, sl));
 	}
 }
@@ -0,0 +1,88 @@
+//! Storage migrations for the simple-crowdfund pallet.
+
+use super::*;
+use frame_support::{
+	pallet_prelude::*,
+	sp_std::marker::PhantomData,
+	traits::{GetStorageVersion, OnRuntimeUpgrade},
+};
+
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+
+const LOG_TARGET: &str = "runtime::simple-crowdfund";
+
+pub mod v1 {
+	use super::*;
+
+	/// `FundInfo` as it was stored before funds recorded their creator and limits.
+	#[derive(Encode, Decode)]
+	pub struct OldFundInfo<AccountId, Balance, BlockNumber> {
+		pub beneficiary: AccountId,
+		pub deposit: Balance,
+		pub raised: Balance,
+		pub end: BlockNumber,
+		pub goal: Balance,
+	}
+
+	type OldFundInfoOf<T> = OldFundInfo<AccountIdOf<T>, BalanceOf<T>, BlockNumberFor<T>>;
+
+	/// Adds `creator` and `limits` to every stored `FundInfo`.
+	///
+	/// Existing funds keep contributions unlimited. Their creator was never recorded, so the
+	/// beneficiary takes its place and may cancel a fund that nobody has backed.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"skipping migration to v1, storage is already at {:?}",
+					on_chain
+				);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0u64;
+			Funds::<T>::translate::<OldFundInfoOf<T>, _>(|_, old| {
+				translated += 1;
+				Some(FundInfo {
+					creator: old.beneficiary.clone(),
+					beneficiary: old.beneficiary,
+					deposit: old.deposit,
+					raised: old.raised,
+					end: old.end,
+					goal: old.goal,
+					limits: FundLimits::default(),
+				})
+			});
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "migrated {} funds to v1", translated);
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			Ok((Funds::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let funds = u32::decode(&mut &state[..])
+				.map_err(|_| TryRuntimeError::Other("invalid pre-upgrade state"))?;
+
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"storage version should be 1 after the migration"
+			);
+			ensure!(
+				Funds::<T>::iter_values().count() as u32 == funds,
+				"every fund should decode after the migration"
+			);
+			Ok(())
+		}
+	}
+}
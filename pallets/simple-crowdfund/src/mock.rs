@@ -1,4 +1,5 @@
 use crate as pallet_simple_crowdfund;
+use account_set::AccountSet;
 use frame_support::{
 	parameter_types,
 	traits::{ConstU16, ConstU64},
@@ -8,6 +9,7 @@ use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
 	BuildStorage,
 };
+use std::collections::BTreeSet;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -67,13 +69,32 @@ parameter_types! {
 	pub const SubmissionDeposit: u64 = 1;
 	pub const MinContribution: u64 = 10;
 	pub const RetirementPeriod: u64 = 5;
+	pub const MaxMilestones: u32 = 4;
+	pub const MaxDescriptionLength: u32 = 32;
+	pub const MilestoneVotingPeriod: u64 = 3;
+	pub const MaxRemovalsPerBlock: u32 = 2;
 }
+/// Accounts 1 and 2 may contribute to funds with an allow-list.
+pub struct AllowList;
+impl AccountSet for AllowList {
+	type AccountId = u64;
+
+	fn accounts() -> BTreeSet<u64> {
+		[1, 2].into_iter().collect()
+	}
+}
+
 impl pallet_simple_crowdfund::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type Currency = Balances;
 	type MinContribution = MinContribution;
 	type SubmissionDeposit = SubmissionDeposit;
 	type RetirementPeriod = RetirementPeriod;
+	type MaxMilestones = MaxMilestones;
+	type AllowList = AllowList;
+	type MaxDescriptionLength = MaxDescriptionLength;
+	type MilestoneVotingPeriod = MilestoneVotingPeriod;
+	type MaxRemovalsPerBlock = MaxRemovalsPerBlock;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -1,5 +1,12 @@
-use crate::{mock::*, Error, FundInfo};
-use frame_support::{assert_err, assert_ok, traits::Hooks};
+use crate::{
+	migrations, mock::*, Error, Event, FundInfo, FundLimits, FundStatus, Funds, Leftover,
+	Leftovers, MilestoneProgress, MilestoneStatus, MilestoneVotes, MilestonesOf,
+};
+use frame_support::{
+	assert_err, assert_ok,
+	traits::{Currency, Get, GetStorageVersion, Hooks, OnRuntimeUpgrade, StorageVersion},
+	weights::Weight,
+};
 
 fn run_to_block(n: u64) {
 	while System::block_number() < n {
@@ -13,6 +20,15 @@ fn run_to_block(n: u64) {
 	}
 }
 
+/// A milestone schedule paying out `amounts` in turn.
+fn schedule(amounts: &[u64]) -> Option<MilestonesOf<Test>> {
+	let milestones: Vec<_> = amounts
+		.iter()
+		.map(|amount| (*amount, b"milestone".to_vec().try_into().unwrap()))
+		.collect();
+	Some(milestones.try_into().unwrap())
+}
+
 #[test]
 fn basic_setup_works() {
 	new_test_ext().execute_with(|| {
@@ -27,10 +43,25 @@ fn basic_setup_works() {
 fn create_works() {
 	new_test_ext().execute_with(|| {
 		// Now try to create a crowdfund campaign
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		assert_eq!(SimpleCrowdfund::fund_count(), 1);
 		// This is what the initial `fund_info` should look like
-		let fund_info = FundInfo { beneficiary: 2, deposit: 1, raised: 0, end: 9, goal: 1000 };
+		let fund_info = FundInfo {
+			creator: 1,
+			beneficiary: 2,
+			deposit: 1,
+			raised: 0,
+			end: 9,
+			goal: 1000,
+			limits: FundLimits::default(),
+		};
 		assert_eq!(SimpleCrowdfund::funds(0), Some(fund_info));
 		// User has deposit removed from their free balance
 		assert_eq!(Balances::free_balance(1), 999);
@@ -43,7 +74,14 @@ fn create_works() {
 fn create_handles_insufficient_balance() {
 	new_test_ext().execute_with(|| {
 		assert_err!(
-			SimpleCrowdfund::create(RuntimeOrigin::signed(1000), 2, 1000, 9),
+			SimpleCrowdfund::create(
+				RuntimeOrigin::signed(1000),
+				2,
+				1000,
+				9,
+				None,
+				FundLimits::default()
+			),
 			pallet_balances::Error::<Test>::InsufficientBalance
 		);
 	})
@@ -53,7 +91,14 @@ fn create_handles_insufficient_balance() {
 fn contribute_works() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		assert_eq!(Balances::free_balance(1), 999);
 		assert_eq!(Balances::free_balance(SimpleCrowdfund::fund_account_id(0)), 1);
 
@@ -88,7 +133,14 @@ fn contribute_handles_basic_errors() {
 		);
 
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 101));
 
 		// Move past end date
@@ -106,7 +158,14 @@ fn contribute_handles_basic_errors() {
 fn withdraw_works() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		// Transfer fees are taken here
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
@@ -119,12 +178,39 @@ fn withdraw_works() {
 		// User can withdraw their full balance without fees
 		assert_ok!(SimpleCrowdfund::withdraw(RuntimeOrigin::signed(1), 0));
 		assert_eq!(Balances::free_balance(1), 999);
+		// The withdrawal is taken off the amount raised
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().raised, 500);
 
 		assert_ok!(SimpleCrowdfund::withdraw(RuntimeOrigin::signed(2), 0));
 		assert_eq!(Balances::free_balance(2), 2000);
 
 		assert_ok!(SimpleCrowdfund::withdraw(RuntimeOrigin::signed(3), 0));
 		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().raised, 0);
+	})
+}
+
+#[test]
+fn withdraw_fails_for_successful_fund() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 1000));
+
+		run_to_block(10);
+
+		// The goal was reached, so the funds belong to the beneficiary
+		assert_err!(
+			SimpleCrowdfund::withdraw(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::SuccessfulFund
+		);
+		assert_eq!(SimpleCrowdfund::contribution_get(0, &3), 1000);
 	})
 }
 
@@ -132,7 +218,14 @@ fn withdraw_works() {
 fn withdraw_handles_basic_errors() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		// Transfer fee is taken here
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 49));
 		assert_eq!(Balances::free_balance(1), 950);
@@ -166,7 +259,14 @@ fn withdraw_handles_basic_errors() {
 fn dissolve_works() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		// Transfer fee is taken here
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
@@ -189,10 +289,12 @@ fn dissolve_works() {
 		// Dissolver account is rewarded
 		assert_eq!(Balances::free_balance(7), 601);
 
-		// Storage trie is removed
-		assert_eq!(SimpleCrowdfund::contribution_get(0, &0), 0);
 		// Fund storage is removed
 		assert_eq!(SimpleCrowdfund::funds(0), None);
+		// Storage trie is removed in the idle time of the block
+		SimpleCrowdfund::on_idle(System::block_number(), Weight::MAX);
+		assert_eq!(SimpleCrowdfund::contribution_get(0, &1), 0);
+		assert_eq!(Leftovers::<Test>::get(0), None);
 	});
 }
 
@@ -200,7 +302,14 @@ fn dissolve_works() {
 fn dissolve_handles_basic_errors() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		// Transfer fee is taken here
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
@@ -231,7 +340,14 @@ fn dissolve_handles_basic_errors() {
 fn dispense_works() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 20, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		// Transfer fee is taken here
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
@@ -257,10 +373,58 @@ fn dispense_works() {
 		// Dispensor account is rewarded deposit
 		assert_eq!(Balances::free_balance(7), 1);
 
-		// Storage trie is removed
-		assert_eq!(SimpleCrowdfund::contribution_get(0, &0), 0);
 		// Fund storage is removed
 		assert_eq!(SimpleCrowdfund::funds(0), None);
+		// Storage trie is removed in the idle time of the block
+		SimpleCrowdfund::on_idle(System::block_number(), Weight::MAX);
+		assert_eq!(SimpleCrowdfund::contribution_get(0, &1), 0);
+		assert_eq!(Leftovers::<Test>::get(0), None);
+	});
+}
+
+#[test]
+fn removed_funds_are_cleared_in_bounded_steps() {
+	let mut ext = new_test_ext();
+	ext.execute_with(|| {
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		for who in 1..=4 {
+			assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(who), 0, 250));
+		}
+		run_to_block(10);
+		assert_ok!(SimpleCrowdfund::dispense(RuntimeOrigin::signed(7), 0));
+
+		// Removing the fund leaves its contributions to `on_idle`
+		assert_eq!(Leftovers::<Test>::get(0), Some(Leftover::Votes(None)));
+		assert_eq!(SimpleCrowdfund::contributions(0).count(), 4);
+	});
+	// Each block clears what was left by the one before
+	ext.commit_all().unwrap();
+
+	ext.execute_with(|| {
+		// Nothing fits in the weight, so nothing is cleared
+		assert_eq!(SimpleCrowdfund::on_idle(10, Weight::zero()), Weight::zero());
+
+		SimpleCrowdfund::on_idle(10, Weight::MAX);
+		assert_eq!(
+			SimpleCrowdfund::contributions(0).count(),
+			4 - MaxRemovalsPerBlock::get() as usize
+		);
+		assert!(matches!(Leftovers::<Test>::get(0), Some(Leftover::Contributions(_))));
+	});
+	ext.commit_all().unwrap();
+
+	ext.execute_with(|| {
+		SimpleCrowdfund::on_idle(11, Weight::MAX);
+		assert_eq!(SimpleCrowdfund::contributions(0).count(), 0);
+		assert_eq!(Leftovers::<Test>::get(0), None);
+		System::assert_last_event(Event::<Test>::Cleared(0).into());
 	});
 }
 
@@ -268,7 +432,14 @@ fn dispense_works() {
 fn dispense_handles_basic_errors() {
 	new_test_ext().execute_with(|| {
 		// Set up a crowdfund
-		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 2, 1000, 9));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
 		// Transfer fee is taken here
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
 		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
@@ -296,3 +467,573 @@ fn dispense_handles_basic_errors() {
 		);
 	});
 }
+
+#[test]
+fn refund_all_works() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 300));
+
+		// Crowdfund is unsuccessful 100 + 200 + 300 < 1000
+		run_to_block(10);
+
+		// Anyone can refund the contributors, a page at a time
+		assert_ok!(SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 2));
+		System::assert_last_event(Event::<Test>::Refunded(0, 2, true).into());
+		assert_ok!(SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 2));
+		System::assert_last_event(Event::<Test>::Refunded(0, 1, false).into());
+
+		// Everybody got their contribution back
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_eq!(Balances::free_balance(2), 2000);
+		assert_eq!(Balances::free_balance(3), 3000);
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().raised, 0);
+		// Only the deposit is left for the dissolver
+		assert_eq!(Balances::free_balance(SimpleCrowdfund::fund_account_id(0)), 1);
+
+		// Nothing is left to refund
+		assert_ok!(SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 2));
+		System::assert_last_event(Event::<Test>::Refunded(0, 0, false).into());
+	})
+}
+
+#[test]
+fn refund_all_handles_basic_errors() {
+	new_test_ext().execute_with(|| {
+		// Set up a crowdfund
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
+
+		// Cannot refund an invalid fund index
+		assert_err!(
+			SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 1, 10),
+			Error::<Test>::InvalidIndex
+		);
+		// Cannot refund an active fund
+		assert_err!(
+			SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 10),
+			Error::<Test>::FundStillActive
+		);
+
+		// Crowdfund is successful 100 + 900 >= 1000
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 900));
+		run_to_block(10);
+
+		// Cannot refund a successful fund
+		assert_err!(
+			SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 10),
+			Error::<Test>::SuccessfulFund
+		);
+	})
+}
+
+#[test]
+fn contributions_can_be_paged() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_eq!(SimpleCrowdfund::contributions_paged(0, None, 10), vec![]);
+
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 100));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 300));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 50));
+
+		// Walk the child trie two contributors at a time
+		let mut listed = Vec::new();
+		let mut start = None;
+		loop {
+			let page = SimpleCrowdfund::contributions_paged(0, start.as_ref(), 2);
+			assert!(page.len() <= 2);
+			let Some(&(last, _)) = page.last() else { break };
+			listed.extend(page);
+			start = Some(last);
+		}
+		listed.sort();
+		assert_eq!(listed, vec![(1, 100), (2, 250), (3, 300)]);
+
+		// Other funds have their own contributors
+		assert_eq!(SimpleCrowdfund::contributions_paged(1, None, 10), vec![]);
+	})
+}
+
+#[test]
+fn fund_info_reports_status() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(SimpleCrowdfund::fund_info(0), None);
+
+		// Fund 0 will miss its goal, fund 1 will reach it
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			2,
+			100,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 500));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 1, 100));
+
+		let status = |index| SimpleCrowdfund::fund_info(index).map(|(_, status)| status);
+		assert_eq!(status(0), Some(FundStatus::Active));
+		assert_eq!(status(1), Some(FundStatus::Active));
+
+		run_to_block(9);
+		assert_eq!(status(0), Some(FundStatus::Failed));
+		assert_eq!(status(1), Some(FundStatus::Succeeded));
+
+		// The retirement period is 5 blocks
+		run_to_block(14);
+		assert_eq!(status(0), Some(FundStatus::Retired));
+		assert_eq!(status(1), Some(FundStatus::Succeeded));
+
+		let (info, _) = SimpleCrowdfund::fund_info(0).unwrap();
+		assert_eq!(info, FundInfo { beneficiary: 2, deposit: 1, raised: 500, end: 9, goal: 1000 });
+	})
+}
+
+#[test]
+fn create_validates_milestones() {
+	new_test_ext().execute_with(|| {
+		// Milestones must add up to the goal
+		assert_err!(
+			SimpleCrowdfund::create(
+				RuntimeOrigin::signed(1),
+				20,
+				1000,
+				9,
+				schedule(&[400, 500]),
+				FundLimits::default()
+			),
+			Error::<Test>::MilestonesDontMatchGoal
+		);
+
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			schedule(&[400, 600]),
+			FundLimits::default()
+		));
+		assert_eq!(SimpleCrowdfund::milestones(0), schedule(&[400, 600]));
+		assert_eq!(
+			SimpleCrowdfund::milestone_progress(0),
+			Some(MilestoneProgress { next: 0, status: MilestoneStatus::Pending })
+		);
+
+		// An empty schedule is the same as none
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			schedule(&[]),
+			FundLimits::default()
+		));
+		assert_eq!(SimpleCrowdfund::milestones(1), None);
+		assert_eq!(SimpleCrowdfund::milestone_progress(1), None);
+	})
+}
+
+#[test]
+fn milestones_are_paid_out_once_approved() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			schedule(&[400, 600]),
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 500));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 600));
+
+		// Cannot claim a milestone before the fund ends
+		assert_err!(
+			SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0),
+			Error::<Test>::FundStillActive
+		);
+
+		// Crowdfund is successful 500 + 600 >= 1000
+		run_to_block(10);
+
+		// The funds can't be dispensed all at once
+		assert_err!(
+			SimpleCrowdfund::dispense(RuntimeOrigin::signed(7), 0),
+			Error::<Test>::HasMilestones
+		);
+		// Only the beneficiary can claim milestones
+		assert_err!(
+			SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NotBeneficiary
+		);
+
+		assert_ok!(SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0));
+		System::assert_last_event(Event::<Test>::MilestoneClaimed(0, 0, 13).into());
+		assert_err!(
+			SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0),
+			Error::<Test>::VoteInProgress
+		);
+
+		// Contributors vote once per milestone, with the weight of their contribution
+		assert_ok!(SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(2), 0, true));
+		System::assert_last_event(Event::<Test>::MilestoneVoted(2, 0, 0, true, 500).into());
+		assert_err!(
+			SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(2), 0, false),
+			Error::<Test>::AlreadyVoted
+		);
+		assert_err!(
+			SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(7), 0, true),
+			Error::<Test>::NoContribution
+		);
+		assert_err!(
+			SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0),
+			Error::<Test>::VotingPeriodActive
+		);
+
+		run_to_block(13);
+		assert_err!(
+			SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(3), 0, false),
+			Error::<Test>::VotingPeriodOver
+		);
+
+		// The first milestone is paid
+		assert_ok!(SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0));
+		System::assert_last_event(Event::<Test>::MilestoneApproved(0, 0, 400).into());
+		assert_eq!(Balances::free_balance(20), 400);
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().raised, 700);
+		assert_eq!(
+			SimpleCrowdfund::fund_info(0).map(|(_, status)| status),
+			Some(FundStatus::Succeeded)
+		);
+
+		// The fund can't be dissolved or withdrawn from while it pays out milestones
+		run_to_block(20);
+		assert_err!(
+			SimpleCrowdfund::dissolve(RuntimeOrigin::signed(7), 0),
+			Error::<Test>::MilestonesInProgress
+		);
+		assert_err!(
+			SimpleCrowdfund::withdraw(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::SuccessfulFund
+		);
+
+		// Nobody votes against the last milestone, so it passes with everything that is left
+		assert_ok!(SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0));
+		run_to_block(23);
+		assert_ok!(SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0));
+		System::assert_last_event(Event::<Test>::Dispensed(0, 23, 7).into());
+		assert_eq!(Balances::free_balance(20), 1100);
+		// The caller collects the deposit
+		assert_eq!(Balances::free_balance(7), 1);
+
+		// Fund storage is removed
+		assert_eq!(SimpleCrowdfund::funds(0), None);
+		assert_eq!(SimpleCrowdfund::milestones(0), None);
+		assert_eq!(SimpleCrowdfund::milestone_progress(0), None);
+		SimpleCrowdfund::on_idle(System::block_number(), Weight::MAX);
+		System::assert_last_event(Event::<Test>::Cleared(0).into());
+		assert_eq!(SimpleCrowdfund::contribution_get(0, &2), 0);
+		assert_eq!(MilestoneVotes::<Test>::iter_prefix(0).count(), 0);
+	})
+}
+
+#[test]
+fn rejected_milestone_refunds_contributors_pro_rata() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			schedule(&[400, 600]),
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 500));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 600));
+		run_to_block(10);
+
+		// The first milestone passes
+		assert_ok!(SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0));
+		run_to_block(13);
+		assert_ok!(SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0));
+
+		// The second is rejected 500 < 600
+		assert_ok!(SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0));
+		assert_ok!(SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(2), 0, true));
+		assert_ok!(SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(3), 0, false));
+		run_to_block(16);
+		assert_ok!(SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0));
+		System::assert_last_event(Event::<Test>::MilestoneRejected(0, 1, 700).into());
+		assert_eq!(Balances::free_balance(20), 400);
+		assert_eq!(
+			SimpleCrowdfund::fund_info(0).map(|(_, status)| status),
+			Some(FundStatus::Failed)
+		);
+
+		// No more milestones can be claimed
+		assert_err!(
+			SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0),
+			Error::<Test>::MilestoneRejected
+		);
+		// The retirement period runs from the rejection
+		assert_err!(
+			SimpleCrowdfund::dissolve(RuntimeOrigin::signed(7), 0),
+			Error::<Test>::FundNotRetired
+		);
+
+		// Contributors share the 700 left in proportion to their 500 and 600
+		assert_ok!(SimpleCrowdfund::withdraw(RuntimeOrigin::signed(2), 0));
+		assert_eq!(Balances::free_balance(2), 1500 + 318);
+		assert_ok!(SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 10));
+		assert_eq!(Balances::free_balance(3), 2400 + 381);
+
+		// Rounding down leaves some dust, which the dissolver collects with the deposit
+		run_to_block(21);
+		assert_eq!(
+			SimpleCrowdfund::fund_info(0).map(|(_, status)| status),
+			Some(FundStatus::Retired)
+		);
+		assert_ok!(SimpleCrowdfund::dissolve(RuntimeOrigin::signed(7), 0));
+		assert_eq!(Balances::free_balance(7), 2);
+	})
+}
+
+#[test]
+fn create_validates_limits() {
+	new_test_ext().execute_with(|| {
+		let limits = FundLimits { cap: Some(900), ..Default::default() };
+		assert_err!(
+			SimpleCrowdfund::create(RuntimeOrigin::signed(1), 20, 1000, 9, None, limits),
+			Error::<Test>::CapBelowGoal
+		);
+		let limits = FundLimits { max_contribution: Some(5), ..Default::default() };
+		assert_err!(
+			SimpleCrowdfund::create(RuntimeOrigin::signed(1), 20, 1000, 9, None, limits),
+			Error::<Test>::MaxContributionTooSmall
+		);
+	})
+}
+
+#[test]
+fn reaching_the_cap_closes_the_fund() {
+	new_test_ext().execute_with(|| {
+		run_to_block(2);
+		let limits = FundLimits { cap: Some(1200), ..Default::default() };
+		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 20, 1000, 9, None, limits));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 500));
+
+		// Cannot contribute past the cap
+		assert_err!(
+			SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 800),
+			Error::<Test>::CapExceeded
+		);
+
+		// Reaching it ends the fund
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 700));
+		System::assert_last_event(Event::<Test>::ClosedEarly(0, 2).into());
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().end, 2);
+		assert_err!(
+			SimpleCrowdfund::contribute(RuntimeOrigin::signed(4), 0, 10),
+			Error::<Test>::ContributionPeriodOver
+		);
+
+		// And it can be dispensed without waiting for the original end
+		assert_ok!(SimpleCrowdfund::dispense(RuntimeOrigin::signed(7), 0));
+		assert_eq!(Balances::free_balance(20), 1200);
+		assert_eq!(Balances::free_balance(7), 1);
+	})
+}
+
+#[test]
+fn too_little_room_under_the_cap_closes_the_fund() {
+	new_test_ext().execute_with(|| {
+		run_to_block(2);
+		let limits = FundLimits { cap: Some(1200), ..Default::default() };
+		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 20, 1000, 9, None, limits));
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 500));
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().end, 9);
+
+		// Less than `MinContribution` is left under the cap, so the fund can never reach it
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 695));
+		System::assert_last_event(Event::<Test>::ClosedEarly(0, 2).into());
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().end, 2);
+		assert_err!(
+			SimpleCrowdfund::contribute(RuntimeOrigin::signed(4), 0, 10),
+			Error::<Test>::ContributionPeriodOver
+		);
+	})
+}
+
+#[test]
+fn contributions_are_limited() {
+	new_test_ext().execute_with(|| {
+		let limits = FundLimits { cap: None, max_contribution: Some(300), allow_list: true };
+		assert_ok!(SimpleCrowdfund::create(RuntimeOrigin::signed(1), 20, 1000, 9, None, limits));
+
+		// Only accounts in the allow-list can contribute
+		assert_err!(
+			SimpleCrowdfund::contribute(RuntimeOrigin::signed(3), 0, 100),
+			Error::<Test>::NotAllowed
+		);
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(1), 0, 200));
+
+		// Each of them up to the maximum contribution in total
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 200));
+		assert_err!(
+			SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 110),
+			Error::<Test>::ContributionTooLarge
+		);
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 100));
+		assert_eq!(SimpleCrowdfund::contribution_get(0, &2), 300);
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().raised, 500);
+	})
+}
+
+#[test]
+fn cancel_works() {
+	new_test_ext().execute_with(|| {
+		run_to_block(1);
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			1000,
+			9,
+			None,
+			FundLimits::default()
+		));
+		assert_eq!(Balances::free_balance(1), 998);
+
+		// Only the creator can cancel
+		assert_err!(
+			SimpleCrowdfund::cancel(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NotCreator
+		);
+
+		// And only before anyone contributes
+		assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(2), 0, 10));
+		assert_err!(
+			SimpleCrowdfund::cancel(RuntimeOrigin::signed(1), 0),
+			Error::<Test>::HasContributions
+		);
+
+		// The creator gets the deposit back
+		assert_ok!(SimpleCrowdfund::cancel(RuntimeOrigin::signed(1), 1));
+		System::assert_last_event(Event::<Test>::Cancelled(1, 1).into());
+		assert_eq!(Balances::free_balance(1), 999);
+		assert_eq!(SimpleCrowdfund::funds(1), None);
+		assert_err!(
+			SimpleCrowdfund::cancel(RuntimeOrigin::signed(1), 1),
+			Error::<Test>::InvalidIndex
+		);
+	})
+}
+
+#[test]
+fn rejected_milestone_refunds_large_balances_exactly() {
+	new_test_ext().execute_with(|| {
+		let contributions = [(5, 700_000_000_000_000_001), (6, 1_300_000_000_000_000_000), (8, 11)];
+		let goal: u64 = contributions.iter().map(|(_, amount)| amount).sum();
+		for (who, amount) in contributions {
+			Balances::make_free_balance_be(&who, amount);
+		}
+		assert_ok!(SimpleCrowdfund::create(
+			RuntimeOrigin::signed(1),
+			20,
+			goal,
+			9,
+			schedule(&[500_000_000_000_000_000, goal - 500_000_000_000_000_000]),
+			FundLimits::default()
+		));
+		for (who, amount) in contributions {
+			assert_ok!(SimpleCrowdfund::contribute(RuntimeOrigin::signed(who), 0, amount));
+		}
+		run_to_block(10);
+
+		// The first milestone passes and the second is rejected
+		assert_ok!(SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0));
+		run_to_block(13);
+		assert_ok!(SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0));
+		assert_ok!(SimpleCrowdfund::claim_milestone(RuntimeOrigin::signed(20), 0));
+		assert_ok!(SimpleCrowdfund::vote_milestone(RuntimeOrigin::signed(6), 0, false));
+		run_to_block(16);
+		assert_ok!(SimpleCrowdfund::conclude_milestone(RuntimeOrigin::signed(7), 0));
+
+		// Each share of the pot is exact to the unit, even for the smallest contribution
+		assert_ok!(SimpleCrowdfund::refund_all(RuntimeOrigin::signed(7), 0, 10));
+		assert_eq!(Balances::free_balance(5), 525_000_000_000_000_001);
+		assert_eq!(Balances::free_balance(6), 975_000_000_000_000_001);
+		assert_eq!(Balances::free_balance(8), 8);
+		assert_eq!(SimpleCrowdfund::funds(0).unwrap().raised, 2);
+	})
+}
+
+#[test]
+fn migration_to_v1_adds_creator_and_limits() {
+	new_test_ext().execute_with(|| {
+		let old = migrations::v1::OldFundInfo::<u64, u64, u64> {
+			beneficiary: 2,
+			deposit: 1,
+			raised: 100,
+			end: 9,
+			goal: 1000,
+		};
+		frame_support::storage::unhashed::put(&<Funds<Test>>::hashed_key_for(0), &old);
+		StorageVersion::new(0).put::<SimpleCrowdfund>();
+
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(SimpleCrowdfund::on_chain_storage_version(), 1);
+		let fund_info = FundInfo {
+			creator: 2,
+			beneficiary: 2,
+			deposit: 1,
+			raised: 100,
+			end: 9,
+			goal: 1000,
+			limits: FundLimits::default(),
+		};
+		assert_eq!(SimpleCrowdfund::funds(0), Some(fund_info));
+	})
+}
@@ -1,6 +1,10 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use account_set::AccountSet;
 pub use pallet::*;
+use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
+
+pub mod migrations;
 
 #[cfg(test)]
 mod mock;
@@ -8,31 +12,67 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
-#[frame_support::pallet(dev_mode)]
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod weights;
+pub use weights::*;
+
+#[frame_support::pallet]
 pub mod pallet {
-	use frame_support::pallet_prelude::*;
+	use frame_support::{pallet_prelude::*, traits::ChangeMembers};
 	use frame_system::pallet_prelude::*;
+	use sp_std::vec::Vec;
+
+	/// The current storage version.
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
-	/// A maximum number of members. When membership reaches this number, no new members may join.
-	pub const MAX_MEMBERS: usize = 16;
-
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Required origin for adding a member.
+		type AddOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Required origin for removing a member.
+		type RemoveOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Required origin for swapping a member for another account.
+		type SwapOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Required origin for replacing all the members at once.
+		type ResetOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// The maximum number of members. When membership reaches this number, no new members
+		/// may join.
+		#[pallet::constant]
+		type MaxMembers: Get<u32>;
+
+		/// Notified with the incoming and outgoing members whenever the set changes.
+		type MembershipChanged: ChangeMembers<Self::AccountId>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
+	/// The members, kept sorted so that lookups can use binary search.
 	#[pallet::storage]
 	#[pallet::getter(fn members)]
-	pub type Members<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+	pub type Members<T: Config> =
+		StorageValue<_, BoundedVec<T::AccountId, T::MaxMembers>, ValueQuery>;
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		MemberAdded(T::AccountId),
 		MemberRemoved(T::AccountId),
+		/// A member has been replaced. [removed, added]
+		MembersSwapped(T::AccountId, T::AccountId),
+		/// All members have been replaced.
+		MembersReset,
 	}
 
 	#[pallet::error]
@@ -42,42 +82,156 @@ pub mod pallet {
 		MembershipLimitReached,
 	}
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+			Self::do_try_state()
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
+		/// Add `who` to the set.
+		///
+		/// May only be called from `T::AddOrigin`.
 		#[pallet::call_index(0)]
-		#[pallet::weight(10_000)]
-		pub fn add_member(origin: OriginFor<T>) -> DispatchResult {
-			let new_member = ensure_signed(origin)?;
+		#[pallet::weight(T::WeightInfo::add_member(T::MaxMembers::get()))]
+		pub fn add_member(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::AddOrigin::ensure_origin(origin)?;
 			let mut members = <Members<T>>::get();
 
-			ensure!(members.len() < MAX_MEMBERS, Error::<T>::MembershipLimitReached);
-
-			match members.binary_search(&new_member) {
+			match members.binary_search(&who) {
 				Ok(_) => return Err(Error::<T>::AlreadyMember.into()),
 				Err(index) => {
-					members.insert(index, new_member.clone());
+					members
+						.try_insert(index, who.clone())
+						.map_err(|_| Error::<T>::MembershipLimitReached)?;
+					T::MembershipChanged::change_members_sorted(&[who.clone()], &[], &members);
 					<Members<T>>::put(members);
-					Self::deposit_event(Event::MemberAdded(new_member));
+					Self::deposit_event(Event::MemberAdded(who));
 					Ok(())
 				},
 			}
 		}
 
+		/// Remove `who` from the set.
+		///
+		/// May only be called from `T::RemoveOrigin`.
 		#[pallet::call_index(1)]
-		#[pallet::weight(10_000)]
-		pub fn remove_member(origin: OriginFor<T>) -> DispatchResult {
-			let old_member = ensure_signed(origin)?;
+		#[pallet::weight(T::WeightInfo::remove_member(T::MaxMembers::get()))]
+		pub fn remove_member(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::RemoveOrigin::ensure_origin(origin)?;
 			let mut members = <Members<T>>::get();
 
-			match members.binary_search(&old_member) {
+			match members.binary_search(&who) {
 				Ok(index) => {
 					members.remove(index);
+					T::MembershipChanged::change_members_sorted(&[], &[who.clone()], &members);
 					<Members<T>>::put(members);
-					Self::deposit_event(Event::MemberRemoved(old_member));
+					Self::deposit_event(Event::MemberRemoved(who));
 					Ok(())
 				},
 				Err(_) => return Err(Error::<T>::NotMember.into()),
 			}
 		}
+
+		/// Replace the member `remove` with `add`. Swapping a member for itself is a no-op.
+		///
+		/// May only be called from `T::SwapOrigin`.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::swap_member(T::MaxMembers::get()))]
+		pub fn swap_member(
+			origin: OriginFor<T>,
+			remove: T::AccountId,
+			add: T::AccountId,
+		) -> DispatchResult {
+			T::SwapOrigin::ensure_origin(origin)?;
+
+			if remove == add {
+				return Ok(())
+			}
+			let mut members = <Members<T>>::get();
+			let index = members.binary_search(&remove).map_err(|_| Error::<T>::NotMember)?;
+			members.remove(index);
+			let index = members.binary_search(&add).err().ok_or(Error::<T>::AlreadyMember)?;
+			// A member was just removed, so there is room for the new one.
+			members
+				.try_insert(index, add.clone())
+				.map_err(|_| Error::<T>::MembershipLimitReached)?;
+			T::MembershipChanged::change_members_sorted(
+				&[add.clone()],
+				&[remove.clone()],
+				&members,
+			);
+			<Members<T>>::put(members);
+
+			Self::deposit_event(Event::MembersSwapped(remove, add));
+			Ok(())
+		}
+
+		/// Replace all the members with `members`. Duplicates are ignored.
+		///
+		/// May only be called from `T::ResetOrigin`.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::reset_members(members.len() as u32, T::MaxMembers::get()))]
+		pub fn reset_members(
+			origin: OriginFor<T>,
+			mut members: Vec<T::AccountId>,
+		) -> DispatchResult {
+			T::ResetOrigin::ensure_origin(origin)?;
+
+			members.sort();
+			members.dedup();
+			ensure!(
+				members.len() <= T::MaxMembers::get() as usize,
+				Error::<T>::MembershipLimitReached
+			);
+
+			T::MembershipChanged::set_members_sorted(&members, &Self::members());
+			<Members<T>>::put(BoundedVec::truncate_from(members));
+			Self::deposit_event(Event::MembersReset);
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Check that the members are sorted and contain no duplicates.
+	#[cfg(any(feature = "try-runtime", test))]
+	pub fn do_try_state() -> Result<(), sp_runtime::TryRuntimeError> {
+		frame_support::ensure!(
+			Self::members().windows(2).all(|pair| pair[0] < pair[1]),
+			"Members are not sorted or contain duplicates"
+		);
+		Ok(())
+	}
+}
+
+impl<T: Config> AccountSet for Pallet<T> {
+	type AccountId = T::AccountId;
+
+	fn accounts() -> BTreeSet<Self::AccountId> {
+		Self::members().into_iter().collect()
+	}
+
+	fn contains(who: &Self::AccountId) -> bool {
+		Self::members().binary_search(who).is_ok()
+	}
+
+	fn count() -> u32 {
+		<Members<T>>::decode_len().unwrap_or_default() as u32
+	}
+
+	fn accounts_paged(start: Option<&Self::AccountId>, limit: u32) -> Vec<Self::AccountId> {
+		let members = Self::members();
+		let from = match start {
+			Some(account) => match members.binary_search(account) {
+				Ok(index) => index + 1,
+				Err(index) => index,
+			},
+			None => 0,
+		};
+		members.into_iter().skip(from).take(limit as usize).collect()
 	}
 }
@@ -0,0 +1,93 @@
+//! Storage migrations for the vec-set pallet.
+
+use super::*;
+use frame_support::{
+	pallet_prelude::*,
+	traits::{ChangeMembers, GetStorageVersion, OnRuntimeUpgrade},
+};
+use sp_std::{marker::PhantomData, vec::Vec};
+
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+
+const LOG_TARGET: &str = "runtime::vec-set";
+
+pub mod v1 {
+	use super::*;
+
+	/// Moves `Members` from an unbounded `Vec` to a `BoundedVec` of at most `MaxMembers`.
+	///
+	/// Legacy storage may hold more members than the new bound, or be out of order. The members
+	/// are sorted and deduplicated, and anything past `MaxMembers` is dropped, keeping the
+	/// lowest account ids. Every dropped account is logged and reported to `MembershipChanged`
+	/// as outgoing.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let on_chain = Pallet::<T>::on_chain_storage_version();
+			if on_chain != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"skipping migration to v1, storage is already at {:?}",
+					on_chain
+				);
+				return T::DbWeight::get().reads(1)
+			}
+
+			let _ = Members::<T>::translate::<Vec<T::AccountId>, _>(|maybe_members| {
+				let mut members = maybe_members.unwrap_or_default();
+				members.sort();
+				members.dedup();
+
+				let max = T::MaxMembers::get() as usize;
+				if members.len() > max {
+					let dropped = members.split_off(max);
+					for who in &dropped {
+						log::warn!(
+							target: LOG_TARGET,
+							"dropping member {:?} over the limit of {}",
+							who,
+							max
+						);
+					}
+					T::MembershipChanged::change_members_sorted(&[], &dropped, &members);
+				}
+				Some(BoundedVec::truncate_from(members))
+			});
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!(target: LOG_TARGET, "migrated members to v1");
+			T::DbWeight::get().reads_writes(2, 2)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let members = frame_support::storage::unhashed::get::<Vec<T::AccountId>>(
+				&Members::<T>::hashed_key(),
+			)
+			.unwrap_or_default();
+			let mut unique = members.clone();
+			unique.sort();
+			unique.dedup();
+			Ok((unique.len() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let unique = u32::decode(&mut &state[..])
+				.map_err(|_| TryRuntimeError::Other("invalid pre-upgrade state"))?;
+
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"storage version should be 1 after the migration"
+			);
+			ensure!(
+				Members::<T>::decode_len().unwrap_or_default() as u32 ==
+					unique.min(T::MaxMembers::get()),
+				"members should be truncated to MaxMembers"
+			);
+			Pallet::<T>::do_try_state()
+		}
+	}
+}
@@ -1,32 +1,46 @@
-use crate::{mock::*, Error, Event, MAX_MEMBERS};
-use frame_support::{assert_err, assert_ok};
+use crate::{migrations, mock::*, Error, Event, Members};
+use account_set::AccountSet;
+use frame_support::{
+	assert_err, assert_noop, assert_ok,
+	traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+};
+use sp_runtime::DispatchError;
 
 #[test]
 fn add_members_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1)));
-		System::assert_last_event(Event::MemberAdded(1).into());
-		assert_eq!(VecSet::members(), vec![1]);
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 10));
+		System::assert_last_event(Event::MemberAdded(10).into());
+		assert_eq!(VecSet::members(), vec![10]);
+	})
+}
+
+#[test]
+fn add_member_requires_add_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(VecSet::add_member(RuntimeOrigin::signed(10), 10), DispatchError::BadOrigin);
+		assert_noop!(VecSet::add_member(RuntimeOrigin::signed(2), 10), DispatchError::BadOrigin);
 	})
 }
 
 #[test]
 fn cant_add_duplicate_member() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1)));
-		assert_err!(VecSet::add_member(RuntimeOrigin::signed(1)), Error::<Test>::AlreadyMember);
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_err!(VecSet::add_member(RuntimeOrigin::signed(1), 10), Error::<Test>::AlreadyMember);
 	});
 }
 
 #[test]
 fn cant_exceed_max_members() {
 	new_test_ext().execute_with(|| {
-		for i in 0..MAX_MEMBERS {
-			assert_ok!(VecSet::add_member(RuntimeOrigin::signed(i.try_into().unwrap())));
+		let max: u32 = MaxMembers::get();
+		for i in 0..max {
+			assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), i.into()));
 		}
 
 		assert_err!(
-			VecSet::add_member(RuntimeOrigin::signed((MAX_MEMBERS + 1).try_into().unwrap())),
+			VecSet::add_member(RuntimeOrigin::signed(1), (max + 1).into()),
 			Error::<Test>::MembershipLimitReached
 		)
 	})
@@ -35,9 +49,10 @@ fn cant_exceed_max_members() {
 #[test]
 fn remove_member_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1)));
-		assert_ok!(VecSet::remove_member(RuntimeOrigin::signed(1)));
-		System::assert_has_event(Event::MemberRemoved(1).into());
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_noop!(VecSet::remove_member(RuntimeOrigin::signed(1), 10), DispatchError::BadOrigin);
+		assert_ok!(VecSet::remove_member(RuntimeOrigin::signed(2), 10));
+		System::assert_has_event(Event::MemberRemoved(10).into());
 		assert_eq!(VecSet::members(), Vec::<u64>::new());
 	})
 }
@@ -45,6 +60,186 @@ fn remove_member_works() {
 #[test]
 fn remove_member_handles_error() {
 	new_test_ext().execute_with(|| {
-		assert_err!(VecSet::remove_member(RuntimeOrigin::signed(1)), Error::<Test>::NotMember)
+		assert_err!(VecSet::remove_member(RuntimeOrigin::signed(2), 10), Error::<Test>::NotMember)
+	})
+}
+
+#[test]
+fn swap_member_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 20));
+
+		assert_noop!(
+			VecSet::swap_member(RuntimeOrigin::signed(1), 10, 30),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			VecSet::swap_member(RuntimeOrigin::signed(3), 30, 40),
+			Error::<Test>::NotMember
+		);
+		assert_noop!(
+			VecSet::swap_member(RuntimeOrigin::signed(3), 10, 20),
+			Error::<Test>::AlreadyMember
+		);
+
+		// Swapping a member for itself does nothing
+		assert_ok!(VecSet::swap_member(RuntimeOrigin::signed(3), 10, 10));
+		assert_eq!(VecSet::members(), vec![10, 20]);
+
+		assert_ok!(VecSet::swap_member(RuntimeOrigin::signed(3), 10, 30));
+		System::assert_last_event(Event::MembersSwapped(10, 30).into());
+		// The members stay sorted
+		assert_eq!(VecSet::members(), vec![20, 30]);
+	})
+}
+
+#[test]
+fn reset_members_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 20));
+
+		assert_noop!(
+			VecSet::reset_members(RuntimeOrigin::signed(1), vec![30]),
+			DispatchError::BadOrigin
+		);
+		let max: u32 = MaxMembers::get();
+		let too_many = (0..=max).map(u64::from).collect();
+		assert_noop!(
+			VecSet::reset_members(RuntimeOrigin::signed(4), too_many),
+			Error::<Test>::MembershipLimitReached
+		);
+
+		assert_ok!(VecSet::reset_members(RuntimeOrigin::signed(4), vec![30, 20, 30]));
+		System::assert_last_event(Event::MembersReset.into());
+		assert_eq!(VecSet::members(), vec![20, 30]);
+
+		// Duplicates don't count towards the limit
+		let mut full: Vec<u64> = (0..max).map(u64::from).collect();
+		full.push(0);
+		assert_ok!(VecSet::reset_members(RuntimeOrigin::signed(4), full));
+		assert_eq!(VecSet::members().len() as u32, max);
+	})
+}
+
+#[test]
+fn members_stay_sorted_and_unique() {
+	new_test_ext().execute_with(|| {
+		for who in [30, 10, 20] {
+			assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), who));
+		}
+		assert_ok!(VecSet::swap_member(RuntimeOrigin::signed(3), 10, 40));
+		assert_ok!(VecSet::do_try_state());
+
+		// Corrupt the storage to check that try_state notices
+		<Members<Test>>::put(frame_support::BoundedVec::truncate_from(vec![2, 1]));
+		assert!(VecSet::do_try_state().is_err());
+	})
+}
+
+/// Write `members` the way the pallet stored them before `Members` was bounded.
+fn put_legacy_members(members: Vec<u64>) {
+	frame_support::storage::unhashed::put(&<Members<Test>>::hashed_key(), &members);
+	StorageVersion::new(0).put::<VecSet>();
+}
+
+#[test]
+fn migration_to_v1_keeps_legacy_members() {
+	new_test_ext().execute_with(|| {
+		put_legacy_members(vec![3, 1, 2, 1]);
+
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(VecSet::on_chain_storage_version(), 1);
+		assert_eq!(VecSet::members(), vec![1, 2, 3]);
+		assert_ok!(VecSet::do_try_state());
+	})
+}
+
+#[test]
+fn migration_to_v1_truncates_oversize_storage() {
+	new_test_ext().execute_with(|| {
+		let max: u32 = MaxMembers::get();
+		// Legacy storage in reverse order and over the limit
+		put_legacy_members((0..max as u64 + 5).rev().collect());
+		MEMBERS.with(|m| *m.borrow_mut() = (0..max as u64 + 5).collect());
+
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		// The lowest account ids are kept, and the dropped ones are reported
+		assert_eq!(VecSet::members(), (0..max as u64).collect::<Vec<_>>());
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), VecSet::members().into_inner());
+
+		// Running it again does nothing
+		assert_ok!(VecSet::remove_member(RuntimeOrigin::signed(2), 0));
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(VecSet::members(), (1..max as u64).collect::<Vec<_>>());
+	})
+}
+
+#[test]
+fn membership_changes_are_reported() {
+	new_test_ext().execute_with(|| {
+		// `TestChangeMembers` checks that each change matches the previous state
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 20));
+		assert_ok!(VecSet::add_member(RuntimeOrigin::signed(1), 10));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10, 20]);
+
+		assert_ok!(VecSet::swap_member(RuntimeOrigin::signed(3), 20, 5));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![5, 10]);
+
+		assert_ok!(VecSet::remove_member(RuntimeOrigin::signed(2), 5));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10]);
+
+		assert_ok!(VecSet::reset_members(RuntimeOrigin::signed(4), vec![30, 10, 20]));
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10, 20, 30]);
+
+		// Failed calls report nothing
+		assert_noop!(
+			VecSet::add_member(RuntimeOrigin::signed(1), 10),
+			Error::<Test>::AlreadyMember
+		);
+		assert_eq!(MEMBERS.with(|m| m.borrow().clone()), vec![10, 20, 30]);
+	})
+}
+
+#[test]
+fn account_set_lists_members() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(VecSet::reset_members(RuntimeOrigin::signed(4), vec![3, 1, 2]));
+		assert_eq!(<VecSet as AccountSet>::accounts(), [1, 2, 3].into_iter().collect());
+	})
+}
+
+#[test]
+fn account_set_contains_and_counts_members() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(<VecSet as AccountSet>::count(), 0);
+		assert_ok!(VecSet::reset_members(RuntimeOrigin::signed(4), vec![3, 1, 2]));
+		assert!(<VecSet as AccountSet>::contains(&2));
+		assert!(!<VecSet as AccountSet>::contains(&4));
+		assert_eq!(<VecSet as AccountSet>::count(), 3);
+	})
+}
+
+#[test]
+fn account_set_pages_through_members() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(VecSet::reset_members(RuntimeOrigin::signed(4), (1..=5).collect()));
+
+		let mut listed = Vec::new();
+		let mut start = None;
+		loop {
+			let page = <VecSet as AccountSet>::accounts_paged(start.as_ref(), 2);
+			assert!(page.len() <= 2);
+			let Some(last) = page.last().cloned() else { break };
+			listed.extend(page);
+			start = Some(last);
+		}
+
+		listed.sort();
+		assert_eq!(listed, vec![1, 2, 3, 4, 5]);
+		assert_eq!(<VecSet as AccountSet>::accounts_paged(None, 0), Vec::<u64>::new());
 	})
 }
@@ -0,0 +1,108 @@
+//! Estimated weights for vec_set
+//!
+//! Nothing in this file was measured. It was written by hand: the storage reads and writes follow
+//! what each call does, but the execution times and proof sizes are guesses. It is not output of
+//! `benchmark pallet`; replace it with generated weights before relying on it. See
+//! `docs/map-set-vs-vec-set.md`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for vec_set.
+pub trait WeightInfo {
+	fn add_member(m: u32, ) -> Weight;
+	fn remove_member(m: u32, ) -> Weight;
+	fn swap_member(m: u32, ) -> Weight;
+	fn reset_members(m: u32, n: u32, ) -> Weight;
+}
+
+/// Hand-written estimates of the weights for vec_set; see the module docs.
+pub struct EstimatedWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for EstimatedWeight<T> {
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn add_member(m: u32, ) -> Weight {
+		// Decoding, shifting and encoding the whole vector
+		Weight::from_parts(12_000_000, 4687)
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn remove_member(m: u32, ) -> Weight {
+		Weight::from_parts(12_000_000, 4687)
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn swap_member(m: u32, ) -> Weight {
+		Weight::from_parts(13_000_000, 4687)
+			.saturating_add(Weight::from_parts(70_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	/// The range of component `n` is `[0, MaxMembers]`.
+	fn reset_members(m: u32, n: u32, ) -> Weight {
+		// Sorting the new members and decoding the `n` old ones
+		Weight::from_parts(14_000_000, 4687)
+			.saturating_add(Weight::from_parts(250_000, 0).saturating_mul(m.into()))
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn add_member(m: u32, ) -> Weight {
+		Weight::from_parts(12_000_000, 4687)
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn remove_member(m: u32, ) -> Weight {
+		Weight::from_parts(12_000_000, 4687)
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	fn swap_member(m: u32, ) -> Weight {
+		Weight::from_parts(13_000_000, 4687)
+			.saturating_add(Weight::from_parts(70_000, 0).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: VecSet Members (r:1 w:1)
+	/// Proof: VecSet Members (max_values: Some(1), max_size: Some(3202), added: 3697, mode: MaxEncodedLen)
+	/// The range of component `m` is `[1, MaxMembers]`.
+	/// The range of component `n` is `[0, MaxMembers]`.
+	fn reset_members(m: u32, n: u32, ) -> Weight {
+		Weight::from_parts(14_000_000, 4687)
+			.saturating_add(Weight::from_parts(250_000, 0).saturating_mul(m.into()))
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+}
@@ -0,0 +1,431 @@
+//! A FIFO ring buffer kept in runtime storage.
+//!
+//! [`RingBufferTransient`] works on top of any `StorageValue` holding the `(start, end)` range and
+//! any `StorageMap` holding the items by index, so a pallet only has to declare those two storage
+//! items to get a queue. [`KeyedRingBufferTransient`] does the same for many queues at once,
+//! keeping the ranges in a `StorageMap` and the items in a `StorageDoubleMap` under a per-queue
+//! key.
+//!
+//! Changes to the range are kept in memory and written back on [`RingBufferTrait::commit`] or when
+//! the transient is dropped.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::marker::PhantomData;
+
+use codec::{Codec, Decode, Encode, EncodeLike, MaxEncodedLen};
+use frame_support::{
+	sp_runtime::traits::Bounded,
+	sp_std::{boxed::Box, vec::Vec},
+	RuntimeDebug, StorageDoubleMap, StorageMap, StorageValue,
+};
+use scale_info::TypeInfo;
+
+/// What to do with an item pushed onto a full buffer.
+#[derive(
+	Encode, Decode, MaxEncodedLen, Clone, Copy, Default, PartialEq, Eq, TypeInfo, RuntimeDebug,
+)]
+pub enum OverflowPolicy {
+	/// Make room by evicting the oldest item.
+	#[default]
+	Overwrite,
+	/// Refuse the item and return [`PushError::Full`].
+	Reject,
+	/// Drop the new item, leaving the buffer untouched.
+	DropNewest,
+}
+
+/// The outcome of a successful [`RingBufferTrait::push`].
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum Pushed<Item> {
+	/// The item was stored and nothing was lost.
+	Stored,
+	/// The item was stored by evicting the oldest item, which is returned.
+	Evicted(Item),
+	/// The buffer was full and the item was dropped. It is handed back.
+	Dropped(Item),
+}
+
+/// Why a [`RingBufferTrait::push`] failed.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum PushError {
+	/// The buffer is full and its policy is [`OverflowPolicy::Reject`].
+	Full,
+}
+
+/// Trait object presenting the ringbuffer interface.
+pub trait RingBufferTrait<Item>
+where
+	Item: Codec + EncodeLike,
+{
+	/// Store all changes made in the underlying storage.
+	///
+	/// Data is not guaranteed to be consistent before this call.
+	///
+	/// Implementation note: Call in `drop` to increase ergonomics.
+	fn commit(&self);
+	/// Push an item onto the end of the queue.
+	///
+	/// If the queue is full, what happens depends on its [`OverflowPolicy`].
+	fn push(&mut self, i: Item) -> Result<Pushed<Item>, PushError>;
+	/// Pop an item from the start of the queue.
+	///
+	/// Returns `None` if the queue is empty.
+	fn pop(&mut self) -> Option<Item>;
+	/// Return whether the queue is empty.
+	fn is_empty(&self) -> bool;
+	/// The number of items in the queue.
+	fn len(&self) -> u64;
+	/// The number of items the queue can hold before it overflows.
+	fn capacity(&self) -> u64;
+	/// The item at the start of the queue, without removing it.
+	fn peek(&self) -> Option<Item>;
+	/// All items of the queue, oldest first, without removing them.
+	///
+	/// Every item is read from storage as the iterator reaches it.
+	fn iter(&self) -> Box<dyn Iterator<Item = Item> + '_>;
+	/// Pop up to `n` items from the start of the queue, oldest first.
+	fn pop_n(&mut self, n: u64) -> Vec<Item> {
+		(0..n).map_while(|_| self.pop()).collect()
+	}
+	/// Remove up to `limit` items from the start of the queue without decoding them.
+	///
+	/// Returns how many were removed. A large queue can be cleared over several calls by
+	/// repeating this until the queue is empty.
+	fn clear(&mut self, limit: u64) -> u64;
+}
+
+// There is no equivalent trait in std so we create one.
+pub trait WrappingOps {
+	fn wrapping_add(self, rhs: Self) -> Self;
+	fn wrapping_sub(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_wrapping_ops {
+	($type:ty) => {
+		impl WrappingOps for $type {
+			fn wrapping_add(self, rhs: Self) -> Self {
+				self.wrapping_add(rhs)
+			}
+
+			fn wrapping_sub(self, rhs: Self) -> Self {
+				self.wrapping_sub(rhs)
+			}
+		}
+	};
+}
+
+impl_wrapping_ops!(u8);
+impl_wrapping_ops!(u16);
+impl_wrapping_ops!(u32);
+impl_wrapping_ops!(u64);
+
+/// The bounds an index type needs to address a ring buffer.
+pub trait RingBufferIndex:
+	Codec + EncodeLike + Copy + Eq + WrappingOps + From<u8> + Bounded + Into<u64>
+{
+}
+
+impl<T> RingBufferIndex for T where
+	T: Codec + EncodeLike + Copy + Eq + WrappingOps + From<u8> + Bounded + Into<u64>
+{
+}
+
+/// Where a ring buffer keeps its `(start, end)` range and its items.
+pub trait RingBufferStorage<Item, Index> {
+	fn range(&self) -> (Index, Index);
+	fn put_range(&self, range: (Index, Index));
+	fn get(&self, index: Index) -> Option<Item>;
+	fn insert(&self, index: Index, item: Item);
+	fn take(&self, index: Index) -> Option<Item>;
+	fn remove(&self, index: Index);
+}
+
+/// A single buffer, with its range in the `StorageValue` `B` and its items in the `StorageMap` `M`.
+pub struct ValueAndMap<B, M>(PhantomData<(B, M)>);
+
+impl<Item, B, M, Index> RingBufferStorage<Item, Index> for ValueAndMap<B, M>
+where
+	Item: Codec + EncodeLike,
+	B: StorageValue<(Index, Index), Query = (Index, Index)>,
+	M: StorageMap<Index, Item, Query = Item>,
+	Index: RingBufferIndex,
+{
+	fn range(&self) -> (Index, Index) {
+		B::get()
+	}
+
+	fn put_range(&self, range: (Index, Index)) {
+		B::put(range)
+	}
+
+	fn get(&self, index: Index) -> Option<Item> {
+		Some(M::get(index))
+	}
+
+	fn insert(&self, index: Index, item: Item) {
+		M::insert(index, item)
+	}
+
+	fn take(&self, index: Index) -> Option<Item> {
+		Some(M::take(index))
+	}
+
+	fn remove(&self, index: Index) {
+		M::remove(index)
+	}
+}
+
+/// One buffer per `key`, with the ranges in the `StorageMap` `B` and the items in the
+/// `StorageDoubleMap` `M`.
+///
+/// An empty buffer keeps no range, so `B` only holds the keys that have items and can be
+/// iterated to find them.
+pub struct KeyedMaps<Key, B, M> {
+	key: Key,
+	_phantom: PhantomData<(B, M)>,
+}
+
+impl<Key, Item, B, M, Index> RingBufferStorage<Item, Index> for KeyedMaps<Key, B, M>
+where
+	Key: Codec + EncodeLike,
+	Item: Codec + EncodeLike,
+	B: StorageMap<Key, (Index, Index), Query = (Index, Index)>,
+	M: StorageDoubleMap<Key, Index, Item, Query = Option<Item>>,
+	Index: RingBufferIndex,
+{
+	fn range(&self) -> (Index, Index) {
+		B::get(&self.key)
+	}
+
+	fn put_range(&self, (start, end): (Index, Index)) {
+		if start == end {
+			B::remove(&self.key)
+		} else {
+			B::insert(&self.key, (start, end))
+		}
+	}
+
+	fn get(&self, index: Index) -> Option<Item> {
+		M::get(&self.key, index)
+	}
+
+	fn insert(&self, index: Index, item: Item) {
+		M::insert(&self.key, index, item)
+	}
+
+	fn take(&self, index: Index) -> Option<Item> {
+		M::take(&self.key, index)
+	}
+
+	fn remove(&self, index: Index) {
+		M::remove(&self.key, index)
+	}
+}
+
+type DefaultIdx = u16;
+
+/// Transient backing data that is the backbone of the trait object.
+///
+/// Use it through [`RingBufferTransient`] or [`KeyedRingBufferTransient`].
+pub struct StorageRingBuffer<Item, S, Index = DefaultIdx>
+where
+	Item: Codec + EncodeLike,
+	S: RingBufferStorage<Item, Index>,
+	Index: RingBufferIndex,
+{
+	storage: S,
+	start: Index,
+	end: Index,
+	policy: OverflowPolicy,
+	_phantom: PhantomData<Item>,
+}
+
+/// A single ring buffer stored in a `StorageValue` and a `StorageMap`.
+pub type RingBufferTransient<Item, B, M, Index = DefaultIdx> =
+	StorageRingBuffer<Item, ValueAndMap<B, M>, Index>;
+
+/// One of many ring buffers, selected by a key, stored in a `StorageMap` and a `StorageDoubleMap`.
+pub type KeyedRingBufferTransient<Key, Item, B, M, Index = DefaultIdx> =
+	StorageRingBuffer<Item, KeyedMaps<Key, B, M>, Index>;
+
+impl<Item, S, Index> StorageRingBuffer<Item, S, Index>
+where
+	Item: Codec + EncodeLike,
+	S: RingBufferStorage<Item, Index>,
+	Index: RingBufferIndex,
+{
+	fn from_storage(storage: S, policy: OverflowPolicy) -> Self {
+		let (start, end) = storage.range();
+		StorageRingBuffer { storage, start, end, policy, _phantom: PhantomData }
+	}
+}
+
+impl<Item, B, M, Index> RingBufferTransient<Item, B, M, Index>
+where
+	Item: Codec + EncodeLike,
+	B: StorageValue<(Index, Index), Query = (Index, Index)>,
+	M: StorageMap<Index, Item, Query = Item>,
+	Index: RingBufferIndex,
+{
+	/// A transient over the current storage that overwrites the oldest item when full.
+	pub fn new() -> RingBufferTransient<Item, B, M, Index> {
+		Self::with_policy(OverflowPolicy::Overwrite)
+	}
+
+	/// A transient over the current storage that handles overflow as `policy` says.
+	pub fn with_policy(policy: OverflowPolicy) -> RingBufferTransient<Item, B, M, Index> {
+		Self::from_storage(ValueAndMap(PhantomData), policy)
+	}
+}
+
+impl<Key, Item, B, M, Index> KeyedRingBufferTransient<Key, Item, B, M, Index>
+where
+	Key: Codec + EncodeLike,
+	Item: Codec + EncodeLike,
+	B: StorageMap<Key, (Index, Index), Query = (Index, Index)>,
+	M: StorageDoubleMap<Key, Index, Item, Query = Option<Item>>,
+	Index: RingBufferIndex,
+{
+	/// A transient over the buffer of `key` that overwrites the oldest item when full.
+	pub fn new(key: Key) -> KeyedRingBufferTransient<Key, Item, B, M, Index> {
+		Self::with_policy(key, OverflowPolicy::Overwrite)
+	}
+
+	/// A transient over the buffer of `key` that handles overflow as `policy` says.
+	pub fn with_policy(
+		key: Key,
+		policy: OverflowPolicy,
+	) -> KeyedRingBufferTransient<Key, Item, B, M, Index> {
+		Self::from_storage(KeyedMaps { key, _phantom: PhantomData }, policy)
+	}
+}
+
+impl<Item, S, Index> Drop for StorageRingBuffer<Item, S, Index>
+where
+	Item: Codec + EncodeLike,
+	S: RingBufferStorage<Item, Index>,
+	Index: RingBufferIndex,
+{
+	fn drop(&mut self) {
+		Self::commit(self);
+	}
+}
+
+impl<Item, S, Index> RingBufferTrait<Item> for StorageRingBuffer<Item, S, Index>
+where
+	Item: Codec + EncodeLike,
+	S: RingBufferStorage<Item, Index>,
+	Index: RingBufferIndex,
+{
+	fn commit(&self) {
+		self.storage.put_range((self.start, self.end))
+	}
+
+	fn push(&mut self, i: Item) -> Result<Pushed<Item>, PushError> {
+		// this will intentionally overflow and wrap around when bonds_end
+		// reaches `Index::max_value` because we want a ringbuffer.
+		let next_index = self.end.wrapping_add(1.into());
+		let mut pushed = Pushed::Stored;
+		if next_index == self.start {
+			// queue would present as empty but is not
+			match self.policy {
+				// --> overwrite the oldest item in the FIFO ringbuffer
+				OverflowPolicy::Overwrite => {
+					if let Some(evicted) = self.storage.take(self.start) {
+						pushed = Pushed::Evicted(evicted);
+					}
+					self.start = self.start.wrapping_add(1.into());
+				},
+				OverflowPolicy::Reject => return Err(PushError::Full),
+				OverflowPolicy::DropNewest => return Ok(Pushed::Dropped(i)),
+			}
+		}
+		self.storage.insert(self.end, i);
+		self.end = next_index;
+
+		Ok(pushed)
+	}
+
+	fn pop(&mut self) -> Option<Item> {
+		if self.is_empty() {
+			return None;
+		}
+		let item = self.storage.take(self.start);
+		self.start = self.start.wrapping_add(1.into());
+
+		item
+	}
+
+	fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+
+	fn len(&self) -> u64 {
+		self.end.wrapping_sub(self.start).into()
+	}
+
+	fn capacity(&self) -> u64 {
+		// One slot always stays free so that a full buffer can be told apart from an empty one.
+		Index::max_value().into()
+	}
+
+	fn peek(&self) -> Option<Item> {
+		if self.is_empty() {
+			return None;
+		}
+		self.storage.get(self.start)
+	}
+
+	fn iter(&self) -> Box<dyn Iterator<Item = Item> + '_> {
+		Box::new(RingBufferIter {
+			storage: &self.storage,
+			next: self.start,
+			end: self.end,
+			_phantom: PhantomData,
+		})
+	}
+
+	fn clear(&mut self, limit: u64) -> u64 {
+		let mut removed = 0;
+		while removed < limit && !self.is_empty() {
+			self.storage.remove(self.start);
+			self.start = self.start.wrapping_add(1.into());
+			removed += 1;
+		}
+		removed
+	}
+}
+
+/// Iterator over the items of a ring buffer, oldest first.
+///
+/// Returned by [`RingBufferTrait::iter`].
+pub struct RingBufferIter<'a, Item, S, Index> {
+	storage: &'a S,
+	next: Index,
+	end: Index,
+	_phantom: PhantomData<Item>,
+}
+
+impl<'a, Item, S, Index> Iterator for RingBufferIter<'a, Item, S, Index>
+where
+	S: RingBufferStorage<Item, Index>,
+	Index: RingBufferIndex,
+{
+	type Item = Item;
+
+	fn next(&mut self) -> Option<Item> {
+		while self.next != self.end {
+			let index = self.next;
+			self.next = self.next.wrapping_add(1.into());
+			// Only an inconsistent buffer has gaps; skip them rather than stopping early.
+			if let Some(item) = self.storage.get(index) {
+				return Some(item)
+			}
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests;
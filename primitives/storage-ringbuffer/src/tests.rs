@@ -0,0 +1,402 @@
+use self::pallet_ringbuffer::SomeStruct;
+
+use super::*;
+use frame_support::traits::{ConstU16, ConstU64};
+use sp_core::H256;
+use sp_runtime::{
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+
+#[allow(unused)]
+#[frame_support::pallet(dev_mode)]
+mod pallet_ringbuffer {
+	use frame_support::pallet_prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	pub type TestIdx = u8;
+
+	#[derive(Clone, PartialEq, Encode, Decode, Default, Debug, TypeInfo)]
+	pub struct SomeStruct {
+		pub foo: u64,
+		pub bar: u64,
+	}
+
+	#[pallet::storage]
+	#[pallet::getter(fn get_test_range)]
+	pub type TestRange<T> = StorageValue<_, (TestIdx, TestIdx), ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn get_test_value)]
+	pub type TestMap<T> = StorageMap<_, Twox64Concat, TestIdx, SomeStruct, ValueQuery>;
+
+	#[pallet::storage]
+	pub type WideRange<T> = StorageValue<_, (u16, u16), ValueQuery>;
+
+	#[pallet::storage]
+	pub type WideMap<T> = StorageMap<_, Twox64Concat, u16, u64, ValueQuery>;
+
+	#[pallet::storage]
+	pub type KeyedRanges<T> = StorageMap<_, Twox64Concat, u64, (TestIdx, TestIdx), ValueQuery>;
+
+	#[pallet::storage]
+	pub type KeyedItems<T> =
+		StorageDoubleMap<_, Twox64Concat, u64, Twox64Concat, TestIdx, SomeStruct, OptionQuery>;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {}
+}
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		RingBufferPallet: pallet_ringbuffer,
+	}
+);
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Nonce = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Block = Block;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+}
+
+impl pallet_ringbuffer::Config for Test {}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+// Trait object that we will be interacting with.
+type RingBuffer = dyn RingBufferTrait<SomeStruct>;
+// Implementation that we will instantiate.
+type Transient = RingBufferTransient<
+	SomeStruct,
+	pallet_ringbuffer::TestRange<Test>,
+	pallet_ringbuffer::TestMap<Test>,
+	pallet_ringbuffer::TestIdx,
+>;
+
+#[test]
+fn simple_push() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		ring.push(pallet_ringbuffer::SomeStruct { foo: 1, bar: 2 }).unwrap();
+		ring.commit();
+		let start_end = RingBufferPallet::get_test_range();
+		assert_eq!(start_end, (0, 1));
+		let some_struct = RingBufferPallet::get_test_value(0);
+		assert_eq!(some_struct, pallet_ringbuffer::SomeStruct { foo: 1, bar: 2 });
+	})
+}
+
+#[test]
+fn drop_does_commit() {
+	new_test_ext().execute_with(|| {
+		{
+			let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+			ring.push(pallet_ringbuffer::SomeStruct { foo: 1, bar: 2 }).unwrap();
+		}
+		let start_end = RingBufferPallet::get_test_range();
+		assert_eq!(start_end, (0, 1));
+		let some_struct = RingBufferPallet::get_test_value(0);
+		assert_eq!(some_struct, pallet_ringbuffer::SomeStruct { foo: 1, bar: 2 });
+	})
+}
+
+#[test]
+fn simple_pop() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+
+		for i in 1..(pallet_ringbuffer::TestIdx::max_value() as u64) + 2 {
+			ring.push(pallet_ringbuffer::SomeStruct { foo: 42, bar: i }).unwrap();
+		}
+		ring.commit();
+		let start_end = RingBufferPallet::get_test_range();
+		assert_eq!(start_end, (1, 0), "range should be inverted because the index wrapped around");
+
+		let item = ring.pop();
+		ring.commit();
+		let (start, end) = RingBufferPallet::get_test_range();
+		assert_eq!(start..end, 2..0);
+		let item = item.expect("an item should be returned");
+		assert_eq!(item.bar, 2, "the struct for field `bar = 2`, was placed at index 1");
+
+		let item = ring.pop();
+		ring.commit();
+		let (start, end) = RingBufferPallet::get_test_range();
+		assert_eq!(start..end, 3..0);
+		let item = item.expect("an item should be returned");
+		assert_eq!(item.bar, 3, "the struct for field `bar = 3`, was placed at index 2");
+
+		for i in 1..4 {
+			ring.push(pallet_ringbuffer::SomeStruct { foo: 21, bar: i }).unwrap();
+		}
+		ring.commit();
+		let start_end = RingBufferPallet::get_test_range();
+		assert_eq!(start_end, (4, 3));
+	})
+}
+
+#[test]
+fn pop_on_empty_returns_none() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		assert!(ring.is_empty());
+		assert_eq!(ring.pop(), None);
+
+		ring.push(SomeStruct { foo: 1, bar: 2 }).unwrap();
+		assert!(!ring.is_empty());
+		assert_eq!(ring.pop(), Some(SomeStruct { foo: 1, bar: 2 }));
+		assert!(ring.is_empty());
+		assert_eq!(ring.pop(), None);
+	})
+}
+
+#[test]
+fn items_come_out_in_fifo_order() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		for bar in 0..10 {
+			ring.push(SomeStruct { foo: 0, bar }).unwrap();
+		}
+		for bar in 0..10 {
+			assert_eq!(ring.pop().map(|item| item.bar), Some(bar));
+		}
+		assert!(ring.is_empty());
+	})
+}
+
+#[test]
+fn popped_items_are_removed_from_storage() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		ring.push(SomeStruct { foo: 1, bar: 2 }).unwrap();
+		ring.pop();
+		ring.commit();
+		assert!(!pallet_ringbuffer::TestMap::<Test>::contains_key(0));
+	})
+}
+
+#[test]
+fn works_with_other_index_types() {
+	new_test_ext().execute_with(|| {
+		type Wide = RingBufferTransient<
+			u64,
+			pallet_ringbuffer::WideRange<Test>,
+			pallet_ringbuffer::WideMap<Test>,
+			u16,
+		>;
+		// Start just before the end of the index space so that the buffer wraps around.
+		pallet_ringbuffer::WideRange::<Test>::put((u16::MAX - 1, u16::MAX - 1));
+
+		let mut ring: Box<dyn RingBufferTrait<u64>> = Box::new(Wide::new());
+		for i in 0..4 {
+			ring.push(i).unwrap();
+		}
+		ring.commit();
+		assert_eq!(pallet_ringbuffer::WideRange::<Test>::get(), (u16::MAX - 1, 2));
+
+		for i in 0..4 {
+			assert_eq!(ring.pop(), Some(i));
+		}
+		assert!(ring.is_empty());
+	})
+}
+
+#[test]
+fn overwrite_evicts_the_oldest_item() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		assert_eq!(ring.capacity(), 255);
+		for bar in 0..255 {
+			assert_eq!(ring.push(SomeStruct { foo: 0, bar }), Ok(Pushed::Stored));
+		}
+		assert_eq!(ring.len(), 255);
+
+		assert_eq!(
+			ring.push(SomeStruct { foo: 0, bar: 255 }),
+			Ok(Pushed::Evicted(SomeStruct { foo: 0, bar: 0 }))
+		);
+		assert_eq!(ring.len(), 255);
+		assert_eq!(ring.pop().map(|item| item.bar), Some(1));
+	})
+}
+
+#[test]
+fn reject_refuses_items_when_full() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::with_policy(OverflowPolicy::Reject));
+		for bar in 0..255 {
+			assert_eq!(ring.push(SomeStruct { foo: 0, bar }), Ok(Pushed::Stored));
+		}
+		assert_eq!(ring.push(SomeStruct { foo: 0, bar: 255 }), Err(PushError::Full));
+		assert_eq!(ring.len(), 255);
+		assert_eq!(ring.pop().map(|item| item.bar), Some(0));
+
+		// Popping makes room again.
+		assert_eq!(ring.push(SomeStruct { foo: 0, bar: 255 }), Ok(Pushed::Stored));
+	})
+}
+
+#[test]
+fn drop_newest_keeps_the_buffer_untouched() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> =
+			Box::new(Transient::with_policy(OverflowPolicy::DropNewest));
+		for bar in 0..255 {
+			ring.push(SomeStruct { foo: 0, bar }).unwrap();
+		}
+		ring.commit();
+		let range = RingBufferPallet::get_test_range();
+
+		let item = SomeStruct { foo: 1, bar: 255 };
+		assert_eq!(ring.push(item.clone()), Ok(Pushed::Dropped(item)));
+		ring.commit();
+		assert_eq!(RingBufferPallet::get_test_range(), range);
+		assert_eq!(ring.pop().map(|item| item.bar), Some(0));
+	})
+}
+
+#[test]
+fn len_follows_the_wrapped_range() {
+	new_test_ext().execute_with(|| {
+		pallet_ringbuffer::TestRange::<Test>::put((250, 250));
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		assert_eq!(ring.len(), 0);
+		for bar in 0..10 {
+			ring.push(SomeStruct { foo: 0, bar }).unwrap();
+		}
+		assert_eq!(ring.len(), 10);
+		ring.pop();
+		assert_eq!(ring.len(), 9);
+	})
+}
+
+#[test]
+fn peek_does_not_remove() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		assert_eq!(ring.peek(), None);
+		ring.push(SomeStruct { foo: 1, bar: 2 }).unwrap();
+		ring.push(SomeStruct { foo: 3, bar: 4 }).unwrap();
+
+		assert_eq!(ring.peek(), Some(SomeStruct { foo: 1, bar: 2 }));
+		assert_eq!(ring.peek(), Some(SomeStruct { foo: 1, bar: 2 }));
+		assert_eq!(ring.len(), 2);
+	})
+}
+
+#[test]
+fn iter_lists_items_in_fifo_order() {
+	new_test_ext().execute_with(|| {
+		// Start near the end of the index space so that iteration has to wrap around.
+		pallet_ringbuffer::TestRange::<Test>::put((253, 253));
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		assert_eq!(ring.iter().count(), 0);
+		for bar in 0..5 {
+			ring.push(SomeStruct { foo: 0, bar }).unwrap();
+		}
+
+		let bars: Vec<_> = ring.iter().map(|item| item.bar).collect();
+		assert_eq!(bars, vec![0, 1, 2, 3, 4]);
+		assert_eq!(ring.len(), 5);
+	})
+}
+
+#[test]
+fn pop_n_stops_when_empty() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		for bar in 0..5 {
+			ring.push(SomeStruct { foo: 0, bar }).unwrap();
+		}
+
+		let bars: Vec<_> = ring.pop_n(3).into_iter().map(|item| item.bar).collect();
+		assert_eq!(bars, vec![0, 1, 2]);
+		assert_eq!(ring.pop_n(10).len(), 2);
+		assert!(ring.is_empty());
+		assert!(ring.pop_n(1).is_empty());
+	})
+}
+
+#[test]
+fn clear_is_bounded_and_resumable() {
+	new_test_ext().execute_with(|| {
+		let mut ring: Box<RingBuffer> = Box::new(Transient::new());
+		for bar in 0..5 {
+			ring.push(SomeStruct { foo: 0, bar }).unwrap();
+		}
+
+		assert_eq!(ring.clear(2), 2);
+		assert_eq!(ring.peek().map(|item| item.bar), Some(2));
+		assert_eq!(ring.clear(10), 3);
+		assert!(ring.is_empty());
+		assert_eq!(ring.clear(10), 0);
+		ring.commit();
+		assert_eq!(pallet_ringbuffer::TestMap::<Test>::iter().count(), 0);
+	})
+}
+
+#[test]
+fn keyed_buffers_are_independent() {
+	new_test_ext().execute_with(|| {
+		type Keyed = KeyedRingBufferTransient<
+			u64,
+			SomeStruct,
+			pallet_ringbuffer::KeyedRanges<Test>,
+			pallet_ringbuffer::KeyedItems<Test>,
+			pallet_ringbuffer::TestIdx,
+		>;
+		{
+			let mut first: Box<RingBuffer> = Box::new(Keyed::new(1));
+			let mut second: Box<RingBuffer> = Box::new(Keyed::new(2));
+			for bar in 0..3 {
+				first.push(SomeStruct { foo: 1, bar }).unwrap();
+			}
+			second.push(SomeStruct { foo: 2, bar: 0 }).unwrap();
+
+			assert_eq!(first.len(), 3);
+			assert_eq!(second.len(), 1);
+			assert_eq!(first.pop(), Some(SomeStruct { foo: 1, bar: 0 }));
+			assert_eq!(second.pop(), Some(SomeStruct { foo: 2, bar: 0 }));
+		}
+
+		assert_eq!(pallet_ringbuffer::KeyedRanges::<Test>::get(1), (1, 3));
+		// Emptied and untouched buffers keep no range
+		assert!(!pallet_ringbuffer::KeyedRanges::<Test>::contains_key(2));
+		let first: Box<RingBuffer> = Box::new(Keyed::new(1));
+		let bars: Vec<_> = first.iter().map(|item| item.bar).collect();
+		assert_eq!(bars, vec![1, 2]);
+		assert!(Keyed::new(3).is_empty());
+		assert!(!pallet_ringbuffer::KeyedRanges::<Test>::contains_key(3));
+	})
+}
@@ -39,6 +39,7 @@ pub use frame_support::{
 	StorageValue,
 };
 pub use frame_system::Call as SystemCall;
+use frame_system::EnsureRoot;
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::{ConstFeeMultiplier, CurrencyAdapter, Multiplier};
@@ -51,7 +52,9 @@ pub use pallet_template;
 
 pub use last_caller;
 
-pub use default_instance;
+pub use double_map;
+
+pub use fixed_point;
 
 /// An index to a block.
 pub type BlockNumber = u32;
@@ -280,14 +283,79 @@ impl pallet_template::Config for Runtime {
 
 impl last_caller::Config<last_caller::Instance1> for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type MaxHistory = ConstU32<16>;
 }
 
 impl last_caller::Config<last_caller::Instance2> for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type MaxHistory = ConstU32<16>;
+}
+
+impl last_caller::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type MaxHistory = ConstU32<16>;
+}
+
+impl double_map::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type MaxRemovalsPerCall = ConstU32<256>;
 }
 
-impl default_instance::Config for Runtime {
+impl fixed_point::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type MaxNameLength = ConstU32<32>;
+	type MaxSnapshots = ConstU32<8>;
+	type MaxAccumulators = ConstU32<16>;
+}
+
+impl map_set::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type AddOrigin = EnsureRoot<AccountId>;
+	type RemoveOrigin = EnsureRoot<AccountId>;
+	type SwapOrigin = EnsureRoot<AccountId>;
+	type ResetOrigin = EnsureRoot<AccountId>;
+	type MaxMembers = ConstU32<100>;
+	type MembershipChanged = ();
+	type WeightInfo = map_set::weights::EstimatedWeight<Runtime>;
+}
+
+impl vec_set::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type AddOrigin = EnsureRoot<AccountId>;
+	type RemoveOrigin = EnsureRoot<AccountId>;
+	type SwapOrigin = EnsureRoot<AccountId>;
+	type ResetOrigin = EnsureRoot<AccountId>;
+	type MaxMembers = ConstU32<100>;
+	type MembershipChanged = ();
+	type WeightInfo = vec_set::weights::EstimatedWeight<Runtime>;
+}
+
+parameter_types! {
+	pub const RingBufferOverflowPolicy: ringbuffer_queue::OverflowPolicy =
+		ringbuffer_queue::OverflowPolicy::Overwrite;
+}
+
+impl ringbuffer_queue::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Item = ringbuffer_queue::ValueStruct;
+	type BufferIndex = u16;
+	type OverflowPolicy = RingBufferOverflowPolicy;
+	type ConsumerOrigin = EnsureRoot<AccountId>;
+	type MaxSubmitters = ConstU32<16>;
+	type ItemProcessor = ();
+}
+
+impl simple_crowdfund::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type SubmissionDeposit = ConstU128<{ 10 * EXISTENTIAL_DEPOSIT }>;
+	type MinContribution = ConstU128<EXISTENTIAL_DEPOSIT>;
+	type RetirementPeriod = ConstU32<DAYS>;
+	type MaxMilestones = ConstU32<16>;
+	type MaxDescriptionLength = ConstU32<256>;
+	type MilestoneVotingPeriod = ConstU32<{ 3 * DAYS }>;
+	type AllowList = MapSet;
+	type MaxRemovalsPerBlock = ConstU32<512>;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -304,10 +372,22 @@ construct_runtime!(
 		TemplateModule: pallet_template,
 		LastCallerInstance1: last_caller::<Instance1>,
 		LastCallerInstance2: last_caller::<Instance2>,
-		DefaultInstance: default_instance,
+		DefaultInstance: last_caller,
+		DoubleMap: double_map,
+		FixedPoint: fixed_point,
+		MapSet: map_set,
+		VecSet: vec_set,
+		RingBufferQueue: ringbuffer_queue,
+		SimpleCrowdfund: simple_crowdfund,
 	}
 );
 
+/// Every instance of `last_caller` in the runtime, for its runtime API.
+///
+/// Keep it in step with `construct_runtime!`; `last_caller_instances_are_all_listed` checks that
+/// no instance is missing.
+pub type LastCallerInstances = (LastCallerInstance1, LastCallerInstance2, DefaultInstance);
+
 /// The address format for describing accounts.
 pub type Address = sp_runtime::MultiAddress<AccountId, ()>;
 /// Block header type as expected by this runtime.
@@ -331,6 +411,17 @@ pub type UncheckedExtrinsic =
 	generic::UncheckedExtrinsic<Address, RuntimeCall, Signature, SignedExtra>;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<RuntimeCall, SignedExtra>;
+/// Storage migrations to run on the next runtime upgrade.
+pub type Migrations = (
+	vec_set::migrations::v1::MigrateToV1<Runtime>,
+	simple_crowdfund::migrations::v1::MigrateToV1<Runtime>,
+	fixed_point::migrations::v1::MigrateToV1<Runtime>,
+	last_caller::migrations::v1::MigrateToV1<Runtime, last_caller::Instance1>,
+	last_caller::migrations::v1::MigrateToV1<Runtime, last_caller::Instance2>,
+	last_caller::migrations::v1::MigrateToV1<Runtime>,
+	ringbuffer_queue::migrations::v1::MigrateToV1<Runtime>,
+);
+
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive::Executive<
 	Runtime,
@@ -338,6 +429,7 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -353,6 +445,8 @@ mod benches {
 		[pallet_timestamp, Timestamp]
 		[pallet_sudo, Sudo]
 		[pallet_template, TemplateModule]
+		[map_set, MapSet]
+		[vec_set, VecSet]
 	);
 }
 
@@ -524,6 +618,77 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl double_map_runtime_api::DoubleMapApi<Block, AccountId> for Runtime {
+		fn top_members(group: u32, k: u32) -> Vec<(AccountId, u32)> {
+			DoubleMap::top_members(group, k)
+		}
+
+		fn group_members(group: u32, start: Option<AccountId>, limit: u32) -> Vec<(AccountId, u32)> {
+			DoubleMap::group_members(group, start, limit)
+		}
+	}
+
+	impl last_caller_runtime_api::LastCallerApi<Block, AccountId, BlockNumber> for Runtime {
+		fn last_calls() -> Vec<(Vec<u8>, Option<(AccountId, BlockNumber)>)> {
+			use last_caller::LastCalls;
+
+			let mut calls = Vec::new();
+			LastCallerInstances::last_calls(&mut calls);
+			calls
+		}
+	}
+
+	impl fixed_point_runtime_api::FixedPointApi<Block> for Runtime {
+		fn compare_drift(factors: Vec<Permill>) -> fixed_point_runtime_api::DriftReport {
+			FixedPoint::compare_drift(factors)
+		}
+	}
+
+	impl ringbuffer_queue_runtime_api::RingBufferQueueApi<
+		Block,
+		AccountId,
+		ringbuffer_queue::ValueStruct,
+	> for Runtime {
+		fn len(owner: AccountId) -> u64 {
+			RingBufferQueue::queue_len(owner)
+		}
+
+		fn peek(
+			owner: AccountId,
+		) -> Option<ringbuffer_queue_runtime_api::QueueItem<AccountId, ringbuffer_queue::ValueStruct>> {
+			RingBufferQueue::peek_item(owner)
+		}
+
+		fn items(
+			owner: AccountId,
+			limit: u32,
+		) -> Vec<ringbuffer_queue_runtime_api::QueueItem<AccountId, ringbuffer_queue::ValueStruct>>
+		{
+			RingBufferQueue::queued_items(owner, limit)
+		}
+	}
+
+	impl simple_crowdfund_runtime_api::SimpleCrowdfundApi<Block, AccountId, Balance, BlockNumber>
+		for Runtime
+	{
+		fn contributions(
+			index: simple_crowdfund_runtime_api::FundIndex,
+			start_key: Option<AccountId>,
+			limit: u32,
+		) -> Vec<(AccountId, Balance)> {
+			SimpleCrowdfund::contributions_paged(index, start_key.as_ref(), limit)
+		}
+
+		fn fund_info(
+			index: simple_crowdfund_runtime_api::FundIndex,
+		) -> Option<(
+			simple_crowdfund_runtime_api::FundInfo<AccountId, Balance, BlockNumber>,
+			simple_crowdfund_runtime_api::FundStatus,
+		)> {
+			SimpleCrowdfund::fund_info(index)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
@@ -587,3 +752,26 @@ impl_runtime_apis! {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::traits::PalletsInfoAccess;
+	use last_caller::LastCalls;
+
+	#[test]
+	fn last_caller_instances_are_all_listed() {
+		let configured: Vec<_> = AllPalletsWithSystem::infos()
+			.into_iter()
+			.filter(|info| info.module_name == "last_caller")
+			.map(|info| info.name.as_bytes().to_vec())
+			.collect();
+
+		let mut calls = Vec::new();
+		frame_support::sp_io::TestExternalities::default()
+			.execute_with(|| LastCallerInstances::last_calls(&mut calls));
+		let listed: Vec<_> = calls.into_iter().map(|(name, _)| name).collect();
+
+		assert_eq!(listed, configured);
+	}
+}
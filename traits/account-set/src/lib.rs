@@ -1,9 +1,419 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use sp_std::collections::btree_set::BTreeSet;
+use frame_support::{
+	sp_runtime::traits::{AtLeast32BitUnsigned, Saturating, Zero},
+	traits::{Currency, EnsureOrigin},
+};
+use frame_system::RawOrigin;
+use sp_std::{
+	collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+	marker::PhantomData,
+	vec::Vec,
+};
 
+/// A set of accounts, such as the members of a group.
+///
+/// Only `accounts` has to be implemented. The other methods have defaults built on it, which
+/// materialise the whole set; implementors that can answer them more cheaply should override them.
 pub trait AccountSet {
-	type AccountId;
+	type AccountId: Ord;
 
+	/// Every account in the set.
 	fn accounts() -> BTreeSet<Self::AccountId>;
+
+	/// Whether `who` is in the set.
+	fn contains(who: &Self::AccountId) -> bool {
+		Self::accounts().contains(who)
+	}
+
+	/// The number of accounts in the set.
+	fn count() -> u32 {
+		Self::accounts().len() as u32
+	}
+
+	/// Up to `limit` accounts of the set, continuing after `start`.
+	///
+	/// The order is up to the implementation but is stable as long as the set doesn't change,
+	/// so passing the last account of the previous page returns the next one. `None` starts from
+	/// the beginning. The default lists the accounts in ascending order.
+	fn accounts_paged(start: Option<&Self::AccountId>, limit: u32) -> Vec<Self::AccountId> {
+		Self::accounts()
+			.into_iter()
+			.skip_while(|account| start.map_or(false, |start| account <= start))
+			.take(limit as usize)
+			.collect()
+	}
+}
+
+/// Accounts that are in `A` or in `B`.
+///
+/// Pages list the accounts of `A` first, then those of `B` that are not in `A`.
+pub struct Union<A, B>(PhantomData<(A, B)>);
+
+impl<A, B> AccountSet for Union<A, B>
+where
+	A: AccountSet,
+	A::AccountId: Clone,
+	B: AccountSet<AccountId = A::AccountId>,
+{
+	type AccountId = A::AccountId;
+
+	fn accounts() -> BTreeSet<Self::AccountId> {
+		let mut accounts = A::accounts();
+		accounts.extend(B::accounts());
+		accounts
+	}
+
+	fn contains(who: &Self::AccountId) -> bool {
+		A::contains(who) || B::contains(who)
+	}
+
+	fn accounts_paged(start: Option<&Self::AccountId>, limit: u32) -> Vec<Self::AccountId> {
+		// An account outside of `A` can only have come from the second half of the listing.
+		let in_b = start.map_or(false, |start| !A::contains(start));
+		let mut page = if in_b { Vec::new() } else { A::accounts_paged(start, limit) };
+
+		let remaining = limit.saturating_sub(page.len() as u32);
+		if remaining > 0 {
+			let b_start = if in_b { start } else { None };
+			page.extend(filter_paged::<B, _>(b_start, remaining, |who| !A::contains(who)));
+		}
+		page
+	}
+}
+
+/// Accounts that are in both `A` and `B`.
+///
+/// Pages follow the order of `A`.
+pub struct Intersection<A, B>(PhantomData<(A, B)>);
+
+impl<A, B> AccountSet for Intersection<A, B>
+where
+	A: AccountSet,
+	A::AccountId: Clone,
+	B: AccountSet<AccountId = A::AccountId>,
+{
+	type AccountId = A::AccountId;
+
+	fn accounts() -> BTreeSet<Self::AccountId> {
+		A::accounts().into_iter().filter(|who| B::contains(who)).collect()
+	}
+
+	fn contains(who: &Self::AccountId) -> bool {
+		A::contains(who) && B::contains(who)
+	}
+
+	fn accounts_paged(start: Option<&Self::AccountId>, limit: u32) -> Vec<Self::AccountId> {
+		filter_paged::<A, _>(start, limit, |who| B::contains(who))
+	}
+}
+
+/// Accounts that are in `A` but not in `B`.
+///
+/// Pages follow the order of `A`.
+pub struct Difference<A, B>(PhantomData<(A, B)>);
+
+impl<A, B> AccountSet for Difference<A, B>
+where
+	A: AccountSet,
+	A::AccountId: Clone,
+	B: AccountSet<AccountId = A::AccountId>,
+{
+	type AccountId = A::AccountId;
+
+	fn accounts() -> BTreeSet<Self::AccountId> {
+		A::accounts().into_iter().filter(|who| !B::contains(who)).collect()
+	}
+
+	fn contains(who: &Self::AccountId) -> bool {
+		A::contains(who) && !B::contains(who)
+	}
+
+	fn accounts_paged(start: Option<&Self::AccountId>, limit: u32) -> Vec<Self::AccountId> {
+		filter_paged::<A, _>(start, limit, |who| !B::contains(who))
+	}
+}
+
+/// Up to `limit` accounts of `S` after `start` for which `keep` holds, in the order of `S`.
+///
+/// Pages of `S` are read until enough accounts are kept, so a selective filter may read many
+/// more than `limit` accounts.
+fn filter_paged<S, F>(start: Option<&S::AccountId>, limit: u32, keep: F) -> Vec<S::AccountId>
+where
+	S: AccountSet,
+	S::AccountId: Clone,
+	F: Fn(&S::AccountId) -> bool,
+{
+	let mut page = Vec::new();
+	let mut cursor = start.cloned();
+	while (page.len() as u32) < limit {
+		let batch = S::accounts_paged(cursor.as_ref(), limit);
+		let Some(last) = batch.last().cloned() else { break };
+		page.extend(batch.into_iter().filter(|who| keep(who)));
+		cursor = Some(last);
+	}
+	// The last account kept is in `S`, so it is a valid place to resume from.
+	page.truncate(limit as usize);
+	page
+}
+
+/// Ensure that the origin is signed by an account of the set `S`, returning the account.
+pub struct EnsureMember<S>(PhantomData<S>);
+
+impl<O, S> EnsureOrigin<O> for EnsureMember<S>
+where
+	O: Into<Result<RawOrigin<S::AccountId>, O>> + From<RawOrigin<S::AccountId>>,
+	S: AccountSet,
+{
+	type Success = S::AccountId;
+
+	fn try_origin(o: O) -> Result<Self::Success, O> {
+		o.into().and_then(|o| match o {
+			RawOrigin::Signed(who) if S::contains(&who) => Ok(who),
+			r => Err(O::from(r)),
+		})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn try_successful_origin() -> Result<O, ()> {
+		let who = S::accounts_paged(None, 1).pop().ok_or(())?;
+		Ok(O::from(RawOrigin::Signed(who)))
+	}
+}
+
+/// A set of accounts that each carry a weight, such as a score or a stake.
+///
+/// Only `weighted_accounts` has to be implemented; as for [`AccountSet`], the defaults materialise
+/// the whole set and may be overridden.
+pub trait WeightedAccountSet {
+	type AccountId: Ord;
+	type Weight: AtLeast32BitUnsigned + Copy;
+
+	/// Every account in the set with its weight.
+	fn weighted_accounts() -> BTreeMap<Self::AccountId, Self::Weight>;
+
+	/// The weight of `who`, or `None` if it isn't in the set.
+	fn weight_of(who: &Self::AccountId) -> Option<Self::Weight> {
+		Self::weighted_accounts().remove(who)
+	}
+
+	/// The sum of the weights of every account, saturating at the maximum.
+	fn total_weight() -> Self::Weight {
+		Self::weighted_accounts()
+			.into_values()
+			.fold(Zero::zero(), |total: Self::Weight, weight| total.saturating_add(weight))
+	}
+}
+
+/// The accounts of `S`, each weighted by its total balance in `C`.
+pub struct BalanceWeighted<S, C>(PhantomData<(S, C)>);
+
+impl<S, C> WeightedAccountSet for BalanceWeighted<S, C>
+where
+	S: AccountSet,
+	C: Currency<S::AccountId>,
+{
+	type AccountId = S::AccountId;
+	type Weight = C::Balance;
+
+	fn weighted_accounts() -> BTreeMap<Self::AccountId, Self::Weight> {
+		S::accounts()
+			.into_iter()
+			.map(|who| {
+				let balance = C::total_balance(&who);
+				(who, balance)
+			})
+			.collect()
+	}
+
+	fn weight_of(who: &Self::AccountId) -> Option<Self::Weight> {
+		S::contains(who).then(|| C::total_balance(who))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Accounts below ten.
+	struct Small;
+	impl AccountSet for Small {
+		type AccountId = u64;
+
+		fn accounts() -> BTreeSet<u64> {
+			(0..10).collect()
+		}
+	}
+
+	/// Even accounts below twenty.
+	struct Even;
+	impl AccountSet for Even {
+		type AccountId = u64;
+
+		fn accounts() -> BTreeSet<u64> {
+			(0..20).step_by(2).collect()
+		}
+	}
+
+	/// Every account of `S`, read two at a time.
+	fn all_pages<S: AccountSet<AccountId = u64>>() -> Vec<u64> {
+		let mut listed = Vec::new();
+		let mut start = None;
+		loop {
+			let page = S::accounts_paged(start.as_ref(), 2);
+			assert!(page.len() <= 2);
+			let Some(&last) = page.last() else { break };
+			listed.extend(page);
+			start = Some(last);
+		}
+		listed
+	}
+
+	#[test]
+	fn defaults_follow_accounts() {
+		assert!(Small::contains(&3));
+		assert!(!Small::contains(&10));
+		assert_eq!(Small::count(), 10);
+		assert_eq!(Small::accounts_paged(Some(&3), 3), vec![4, 5, 6]);
+		assert_eq!(all_pages::<Small>(), (0..10).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn union_works() {
+		type S = Union<Small, Even>;
+		assert!(S::contains(&5) && S::contains(&12) && !S::contains(&13));
+		assert_eq!(S::count(), 15);
+		assert_eq!(all_pages::<S>(), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 16, 18]);
+	}
+
+	#[test]
+	fn intersection_works() {
+		type S = Intersection<Small, Even>;
+		assert!(S::contains(&4) && !S::contains(&5) && !S::contains(&12));
+		assert_eq!(S::accounts(), [0, 2, 4, 6, 8].into_iter().collect());
+		assert_eq!(all_pages::<S>(), vec![0, 2, 4, 6, 8]);
+	}
+
+	#[test]
+	fn difference_works() {
+		type S = Difference<Small, Even>;
+		assert!(S::contains(&5) && !S::contains(&4) && !S::contains(&13));
+		assert_eq!(S::accounts(), [1, 3, 5, 7, 9].into_iter().collect());
+		assert_eq!(all_pages::<S>(), vec![1, 3, 5, 7, 9]);
+		assert_eq!(Difference::<Even, Small>::accounts_paged(None, 3), vec![10, 12, 14]);
+	}
+
+	/// Accounts below four, weighted by their id.
+	struct Scores;
+	impl WeightedAccountSet for Scores {
+		type AccountId = u64;
+		type Weight = u32;
+
+		fn weighted_accounts() -> BTreeMap<u64, u32> {
+			(0..4).map(|who| (who, who as u32)).collect()
+		}
+	}
+
+	#[test]
+	fn weighted_defaults_follow_weighted_accounts() {
+		assert_eq!(Scores::weight_of(&3), Some(3));
+		assert_eq!(Scores::weight_of(&0), Some(0));
+		assert_eq!(Scores::weight_of(&4), None);
+		assert_eq!(Scores::total_weight(), 6);
+	}
+
+	mod balances {
+		use frame_support::traits::{ConstU16, ConstU32, ConstU64};
+		use sp_core::H256;
+		use sp_runtime::{
+			traits::{BlakeTwo256, IdentityLookup},
+			BuildStorage,
+		};
+
+		type Block = frame_system::mocking::MockBlock<Test>;
+
+		frame_support::construct_runtime!(
+			pub enum Test
+			{
+				System: frame_system,
+				Balances: pallet_balances,
+			}
+		);
+
+		impl frame_system::Config for Test {
+			type BaseCallFilter = frame_support::traits::Everything;
+			type BlockWeights = ();
+			type BlockLength = ();
+			type DbWeight = ();
+			type RuntimeOrigin = RuntimeOrigin;
+			type RuntimeCall = RuntimeCall;
+			type Nonce = u64;
+			type Hash = H256;
+			type Hashing = BlakeTwo256;
+			type AccountId = u64;
+			type Lookup = IdentityLookup<Self::AccountId>;
+			type Block = Block;
+			type RuntimeEvent = RuntimeEvent;
+			type BlockHashCount = ConstU64<250>;
+			type Version = ();
+			type PalletInfo = PalletInfo;
+			type AccountData = pallet_balances::AccountData<u64>;
+			type OnNewAccount = ();
+			type OnKilledAccount = ();
+			type SystemWeightInfo = ();
+			type SS58Prefix = ConstU16<42>;
+			type OnSetCode = ();
+			type MaxConsumers = ConstU32<16>;
+		}
+
+		impl pallet_balances::Config for Test {
+			type RuntimeEvent = RuntimeEvent;
+			type WeightInfo = ();
+			type Balance = u64;
+			type DustRemoval = ();
+			type ExistentialDeposit = ConstU64<1>;
+			type AccountStore = System;
+			type ReserveIdentifier = [u8; 8];
+			type RuntimeHoldReason = ();
+			type FreezeIdentifier = ();
+			type MaxLocks = ();
+			type MaxReserves = ();
+			type MaxHolds = ();
+			type MaxFreezes = ();
+		}
+
+		/// Accounts 1 to 4 with 10 times their id, and account 20 outside of `Small`.
+		pub fn new_test_ext() -> sp_io::TestExternalities {
+			let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+			pallet_balances::GenesisConfig::<Test> {
+				balances: vec![(1, 10), (2, 20), (3, 30), (4, 40), (20, 200)],
+			}
+			.assimilate_storage(&mut t)
+			.unwrap();
+			t.into()
+		}
+	}
+
+	#[test]
+	fn balance_weighted_follows_free_balances() {
+		use balances::{new_test_ext, Balances, Test};
+		type S = BalanceWeighted<Small, Balances>;
+
+		new_test_ext().execute_with(|| {
+			assert_eq!(S::weight_of(&3), Some(Balances::free_balance(3)));
+			assert_eq!(S::weight_of(&3), Some(30));
+			// Members of `Small` without an account weigh nothing
+			assert_eq!(S::weight_of(&7), Some(0));
+			// Accounts outside of `Small` are left out, whatever their balance
+			assert_eq!(S::weight_of(&20), None);
+
+			let weights = S::weighted_accounts();
+			assert_eq!(weights.len(), 10);
+			assert!(!weights.contains_key(&20));
+			for who in 0..10 {
+				assert_eq!(weights[&who], pallet_balances::Pallet::<Test>::free_balance(who));
+			}
+			assert_eq!(S::total_weight(), 100);
+		})
+	}
 }